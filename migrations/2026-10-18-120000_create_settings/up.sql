@@ -0,0 +1,4 @@
+create table settings (
+    key text not null primary key,
+    value text not null
+);
@@ -0,0 +1,10 @@
+update settings set key = 'last_fired_slot' where key = 'last_fired_slot:1';
+
+drop index posts_target_id_idx;
+
+alter table posts drop column target_id;
+
+drop table sender_targets;
+
+drop index targets_name_idx;
+drop table targets;
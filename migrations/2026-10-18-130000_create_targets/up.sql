@@ -0,0 +1,25 @@
+create table targets (
+    id integer not null primary key autoincrement,
+    name text not null,
+    chat_id bigint not null,
+    interval_secs bigint null,
+    schedule text null,
+    group_threshold bigint not null default 0
+);
+
+create unique index targets_name_idx on targets(name);
+
+-- placeholder for the target configured on the command line, synced on startup
+insert into targets (id, name, chat_id) values (1, 'default', 0);
+
+create table sender_targets (
+    chat_id bigint not null primary key,
+    target_id integer not null,
+    foreign key (target_id) references targets(id)
+);
+
+alter table posts add column target_id integer not null default 1;
+
+create index posts_target_id_idx on posts(target_id);
+
+update settings set key = 'last_fired_slot:1' where key = 'last_fired_slot';
@@ -0,0 +1,3 @@
+drop index posts_priority_idx;
+
+alter table posts drop column priority;
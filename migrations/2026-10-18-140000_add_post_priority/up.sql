@@ -0,0 +1,3 @@
+alter table posts add column priority integer not null default 0;
+
+create index posts_priority_idx on posts(priority);
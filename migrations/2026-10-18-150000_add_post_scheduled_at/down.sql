@@ -0,0 +1,3 @@
+drop index posts_scheduled_at_idx;
+
+alter table posts drop column scheduled_at;
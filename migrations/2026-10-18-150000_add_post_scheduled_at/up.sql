@@ -0,0 +1,3 @@
+alter table posts add column scheduled_at timestamp null;
+
+create index posts_scheduled_at_idx on posts(scheduled_at);
@@ -0,0 +1,6 @@
+drop index posts_failed_idx;
+
+alter table posts drop column failed;
+alter table posts drop column next_attempt_at;
+alter table posts drop column last_error;
+alter table posts drop column attempts;
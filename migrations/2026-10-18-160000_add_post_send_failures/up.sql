@@ -0,0 +1,6 @@
+alter table posts add column attempts integer not null default 0;
+alter table posts add column last_error text null;
+alter table posts add column next_attempt_at timestamp null;
+alter table posts add column failed bool not null default false;
+
+create index posts_failed_idx on posts(failed);
@@ -0,0 +1,6 @@
+alter table targets drop column caption_template;
+
+alter table posts drop column tags;
+alter table posts drop column source_url;
+alter table posts drop column contributor;
+alter table posts drop column caption_template;
@@ -0,0 +1,6 @@
+alter table posts add column caption_template text null;
+alter table posts add column contributor text null;
+alter table posts add column source_url text null;
+alter table posts add column tags text null;
+
+alter table targets add column caption_template text null;
@@ -0,0 +1,2 @@
+alter table posts drop column caption_entities;
+alter table posts drop column caption;
@@ -0,0 +1,2 @@
+alter table posts add column caption text null;
+alter table posts add column caption_entities text null;
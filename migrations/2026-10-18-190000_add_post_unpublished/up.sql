@@ -0,0 +1 @@
+alter table posts add column unpublished bool not null default false;
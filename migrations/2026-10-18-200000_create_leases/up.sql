@@ -0,0 +1,6 @@
+create table leases (
+    name text not null primary key,
+    holder text not null,
+    heartbeat_at timestamp not null,
+    expires_at timestamp not null
+);
@@ -0,0 +1,11 @@
+create table post_items (
+    post_id uuid_text not null,
+    position integer not null,
+    media_type media_type_text not null,
+    file_id text not null,
+    image_hash text null,
+    primary key (post_id, position),
+    foreign key (post_id) references posts(id)
+);
+
+create index post_items_image_hash_idx on post_items(image_hash);
@@ -0,0 +1 @@
+alter table posts add column spoiler bool not null default false;
@@ -0,0 +1,4 @@
+alter table targets drop column protect_content;
+alter table targets drop column silent_hours;
+alter table posts drop column protect_content;
+alter table posts drop column silent;
@@ -0,0 +1,4 @@
+alter table posts add column silent bool null;
+alter table posts add column protect_content bool null;
+alter table targets add column silent_hours text null;
+alter table targets add column protect_content bool null;
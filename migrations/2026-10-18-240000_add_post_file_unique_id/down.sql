@@ -0,0 +1,3 @@
+drop index posts_file_unique_id_idx;
+
+alter table posts drop column file_unique_id;
@@ -0,0 +1,3 @@
+alter table posts add column file_unique_id text null;
+
+create index posts_file_unique_id_idx on posts(file_unique_id);
@@ -0,0 +1 @@
+alter table post_items drop column spoiler;
@@ -0,0 +1,3 @@
+alter table post_items add column spoiler bool not null default false;
+
+update post_items set spoiler = (select spoiler from posts where posts.id = post_items.post_id);
@@ -0,0 +1,7 @@
+drop index post_items_clip_fingerprint_idx;
+drop index post_items_file_unique_id_idx;
+drop index posts_clip_fingerprint_idx;
+
+alter table post_items drop column clip_fingerprint;
+alter table post_items drop column file_unique_id;
+alter table posts drop column clip_fingerprint;
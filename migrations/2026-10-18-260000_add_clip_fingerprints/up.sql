@@ -0,0 +1,10 @@
+alter table posts add column clip_fingerprint text null;
+alter table post_items add column file_unique_id text null;
+alter table post_items add column clip_fingerprint text null;
+
+create index posts_clip_fingerprint_idx on posts(clip_fingerprint);
+create index post_items_file_unique_id_idx on post_items(file_unique_id);
+create index post_items_clip_fingerprint_idx on post_items(clip_fingerprint);
+
+-- Thumbnail hashes alone are superseded by fingerprints.
+update posts set image_hash = null where media_type in ('video', 'anim');
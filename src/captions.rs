@@ -0,0 +1,476 @@
+use crate::{
+    config::Config,
+    database::{MediaType, Post, Target},
+    utils::{CAPTION_MAX_LENGTH, MESSAGE_MAX_LENGTH, telegram_len},
+};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::cmp::Reverse;
+use teloxide::{
+    types::{Message, MessageEntity, MessageEntityKind, ParseMode},
+    utils::{html, markdown},
+};
+
+/// A line on its own separating the part of a submission caption published to
+/// the channel (above) from control keywords such as `force` and target tags
+/// (below). Captions without it are published as they are, minus control
+/// keywords in front of or after the text; see [`strip_keywords`].
+pub const CAPTION_SEPARATOR: &str = "//";
+
+/// Caption templates from the configuration: a default one and optional
+/// overrides per media type.
+///
+/// Templates are written in the configured parse mode and may contain
+/// `{signature}`, `{link}`, `{date}`, `{contributor}`, `{tags}`, `{source}`
+/// and `{caption}` placeholders. The contributor's caption is put above the
+/// template unless it uses `{caption}`. An empty template adds nothing.
+#[derive(Debug, Clone, Default)]
+pub struct CaptionTemplates {
+    pub default: Option<String>,
+    pub photo: Option<String>,
+    pub video: Option<String>,
+    pub animation: Option<String>,
+}
+
+impl CaptionTemplates {
+    pub fn for_media_type(&self, media_type: &MediaType) -> Option<&str> {
+        let specific = match media_type {
+            MediaType::Photo | MediaType::Document => &self.photo,
+            MediaType::Video => &self.video,
+            MediaType::Animation => &self.animation,
+            MediaType::Album
+            | MediaType::Text
+            | MediaType::Audio
+            | MediaType::Voice
+            | MediaType::Sticker
+            | MediaType::VideoNote => &None,
+        };
+        specific.as_deref().or(self.default.as_deref())
+    }
+}
+
+/// Picks the template for a post: the post's own override wins over the
+/// target's template, which wins over the configured ones.
+pub fn caption_template<'a>(
+    cfg: &'a Config,
+    target: &'a Target,
+    post: &'a Post,
+) -> Option<&'a str> {
+    post.caption_template
+        .as_deref()
+        .or(target.caption_template.as_deref())
+        .or_else(|| cfg.caption_templates.for_media_type(&post.media_type))
+}
+
+/// Longest caption Telegram accepts for `media_type`; text posts are sent as
+/// messages.
+pub fn caption_limit(media_type: &MediaType) -> usize {
+    match media_type {
+        MediaType::Text => MESSAGE_MAX_LENGTH,
+        _ => CAPTION_MAX_LENGTH,
+    }
+}
+
+/// Renders the caption of `post` published at `published_at`, or `None` when
+/// it should go out without one.
+///
+/// The contributor's caption is shortened so the whole caption stays within
+/// [`caption_limit`]. The rest of the template is measured with its markup,
+/// which errs on the short side.
+pub fn render_caption(
+    cfg: &Config,
+    target: &Target,
+    post: &Post,
+    published_at: DateTime<Tz>,
+) -> Option<String> {
+    let parse_mode = cfg.caption_parse_mode;
+    let template = caption_template(cfg, target, post).unwrap_or_default();
+
+    let value = |name: &str, contributor_caption: &str| -> Option<String> {
+        let value = match name {
+            "signature" => cfg.channel_signature.clone(),
+            "link" => cfg.channel_link.clone(),
+            "date" => Some(published_at.format("%Y-%m-%d").to_string()),
+            "contributor" => post.contributor.clone(),
+            "tags" => post.tags.clone(),
+            "source" => post.source_url.clone(),
+            "caption" => return Some(contributor_caption.to_string()),
+            _ => return None,
+        };
+        Some(escape(&value.unwrap_or_default(), parse_mode))
+    };
+
+    let frame = fill_template(template, |name| value(name, ""));
+    let separator = if template.contains("{caption}") { 0 } else { 2 };
+    let budget =
+        caption_limit(&post.media_type).saturating_sub(telegram_len(frame.trim()) + separator);
+    let contributor_caption = post
+        .caption
+        .as_deref()
+        .map(|text| {
+            let (text, entities) = truncate(text, &post.entities(), budget);
+            render_entities(&text, &entities, parse_mode)
+        })
+        .unwrap_or_default();
+
+    let mut caption = fill_template(template, |name| value(name, &contributor_caption));
+    if !template.contains("{caption}") && !contributor_caption.is_empty() {
+        caption = format!("{contributor_caption}\n\n{}", caption.trim());
+    }
+
+    let caption = caption.trim();
+    (!caption.is_empty()).then(|| caption.to_string())
+}
+
+/// Replaces the `{name}` placeholders of `template` for which `value` has a
+/// value, leaving other braces as they are.
+fn fill_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        match tail
+            .find('}')
+            .and_then(|end| value(&tail[1..end]).map(|v| (end, v)))
+        {
+            Some((end, v)) => {
+                result.push_str(&v);
+                rest = &tail[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Shortens `text` to at most `max_length` UTF-16 code units, ending it with
+/// an ellipsis, and clips its entities to match.
+fn truncate(
+    text: &str,
+    entities: &[MessageEntity],
+    max_length: usize,
+) -> (String, Vec<MessageEntity>) {
+    if telegram_len(text) <= max_length {
+        return (text.to_string(), entities.to_vec());
+    }
+    if max_length == 0 {
+        return (String::new(), vec![]);
+    }
+
+    let mut length = 0;
+    let end = text
+        .char_indices()
+        .find(|(_, c)| {
+            length += c.len_utf16();
+            length > max_length - 1
+        })
+        .map_or(text.len(), |(i, _)| i);
+    let kept = text[..end].trim_end();
+    (format!("{kept}…"), clip_entities(kept, entities))
+}
+
+/// Splits a submission caption at a [`CAPTION_SEPARATOR`] line into the part
+/// above it, trimmed, and the part below it.
+pub fn split_caption(caption: &str) -> Option<(&str, &str)> {
+    let mut line_start = 0;
+    for line in caption.split_inclusive('\n') {
+        if line.trim() == CAPTION_SEPARATOR {
+            let published = caption[..line_start].trim_end();
+            let control = &caption[line_start + line.len()..];
+            return Some((published, control));
+        }
+        line_start += line.len();
+    }
+    None
+}
+
+/// Splits the text of a text post into its body and the control keywords.
+/// Unlike captions, text without a separator is all body.
+pub fn split_text(text: &str) -> (&str, &str) {
+    split_caption(text).unwrap_or((text, ""))
+}
+
+/// The control keywords of a submission's caption, or of its text for text
+/// posts. A caption without a separator is control keywords and text alike.
+pub fn caption_control(message: &Message) -> &str {
+    match (message.caption(), message.text()) {
+        (Some(caption), _) => split_caption(caption).map_or(caption, |(_, control)| control),
+        (None, Some(text)) => split_text(text).1,
+        (None, None) => "",
+    }
+}
+
+/// Removes the words `is_keyword` recognises from the start and the end of
+/// `text`, with its entities rebased to match. Keywords within the text are
+/// kept, as they are more likely to be meant literally.
+pub fn strip_keywords<'a>(
+    text: &'a str,
+    entities: &[MessageEntity],
+    is_keyword: impl Fn(&str) -> bool,
+) -> (&'a str, Vec<MessageEntity>) {
+    let mut words = vec![];
+    let mut word_start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(start)) => {
+                words.push((start, i));
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(start) = word_start {
+        words.push((start, text.len()));
+    }
+
+    let is_text = |(start, end): &&(usize, usize)| !is_keyword(&text[*start..*end]);
+    let (Some((start, _)), Some((_, end))) =
+        (words.iter().find(is_text), words.iter().rfind(is_text))
+    else {
+        return ("", vec![]);
+    };
+
+    let kept = &text[*start..*end];
+    let rebased = suffix_entities(text, entities, &text[*start..]);
+    (kept, clip_entities(kept, &rebased))
+}
+
+/// Entities of a caption that fall within its prefix `text`, clipped to it.
+pub fn clip_entities(text: &str, entities: &[MessageEntity]) -> Vec<MessageEntity> {
+    let length = text.encode_utf16().count();
+    entities
+        .iter()
+        .filter(|entity| entity.offset < length)
+        .map(|entity| MessageEntity {
+            length: entity.length.min(length - entity.offset),
+            ..entity.clone()
+        })
+        .collect()
+}
+
+/// Entities of `text` that fall within its trailing `suffix`, such as the
+/// arguments of a command, rebased onto the suffix.
+pub fn suffix_entities(text: &str, entities: &[MessageEntity], suffix: &str) -> Vec<MessageEntity> {
+    let Some(prefix) = text.strip_suffix(suffix) else {
+        return vec![];
+    };
+    let start = prefix.encode_utf16().count();
+    let rebased: Vec<MessageEntity> = entities
+        .iter()
+        .filter(|entity| entity.offset >= start)
+        .map(|entity| MessageEntity {
+            offset: entity.offset - start,
+            ..entity.clone()
+        })
+        .collect();
+    clip_entities(suffix, &rebased)
+}
+
+fn escape(text: &str, parse_mode: ParseMode) -> String {
+    match parse_mode {
+        ParseMode::MarkdownV2 => markdown::escape(text),
+        _ => html::escape(text),
+    }
+}
+
+/// Renders `text` with its Telegram entities as markup for `parse_mode`.
+pub fn render_entities(text: &str, entities: &[MessageEntity], parse_mode: ParseMode) -> String {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut entities: Vec<&MessageEntity> = entities.iter().collect();
+    entities.sort_by_key(|entity| (entity.offset, Reverse(entity.length)));
+    render_range(&units, 0, units.len(), &entities, parse_mode)
+}
+
+/// Renders `units[start..end]`; `entities` are sorted and lie within the
+/// range. Telegram entities either nest or do not overlap at all.
+fn render_range(
+    units: &[u16],
+    start: usize,
+    end: usize,
+    entities: &[&MessageEntity],
+    parse_mode: ParseMode,
+) -> String {
+    let slice = |from: usize, to: usize| String::from_utf16_lossy(&units[from..to]);
+
+    let mut result = String::new();
+    let mut cursor = start;
+    let mut i = 0;
+    while i < entities.len() {
+        let entity = entities[i];
+        if entity.offset < cursor || entity.offset >= end {
+            i += 1;
+            continue;
+        }
+        let entity_end = (entity.offset + entity.length).min(end);
+        let children_end = entities[i + 1..]
+            .iter()
+            .position(|child| child.offset >= entity_end)
+            .map_or(entities.len(), |p| i + 1 + p);
+
+        result.push_str(&escape(&slice(cursor, entity.offset), parse_mode));
+        let raw = slice(entity.offset, entity_end);
+        let inner = render_range(
+            units,
+            entity.offset,
+            entity_end,
+            &entities[i + 1..children_end],
+            parse_mode,
+        );
+        result.push_str(&match parse_mode {
+            ParseMode::MarkdownV2 => wrap_markdown(&entity.kind, &raw, &inner),
+            _ => wrap_html(&entity.kind, &raw, &inner),
+        });
+
+        cursor = entity_end;
+        i = children_end;
+    }
+    result.push_str(&escape(&slice(cursor, end), parse_mode));
+    result
+}
+
+fn wrap_html(kind: &MessageEntityKind, raw: &str, inner: &str) -> String {
+    match kind {
+        MessageEntityKind::Bold => html::bold(inner),
+        MessageEntityKind::Italic => html::italic(inner),
+        MessageEntityKind::Underline => html::underline(inner),
+        MessageEntityKind::Strikethrough => html::strike(inner),
+        MessageEntityKind::Spoiler => format!("<tg-spoiler>{inner}</tg-spoiler>"),
+        MessageEntityKind::Blockquote => html::blockquote(inner),
+        MessageEntityKind::Code => html::code_inline(raw),
+        MessageEntityKind::Pre {
+            language: Some(language),
+        } => html::code_block_with_lang(raw, language),
+        MessageEntityKind::Pre { language: None } => html::code_block(raw),
+        MessageEntityKind::TextLink { url } => html::link(url.as_str(), inner),
+        MessageEntityKind::TextMention { user } => html::user_mention(user.id, inner),
+        MessageEntityKind::CustomEmoji { custom_emoji_id } => {
+            format!("<tg-emoji emoji-id=\"{custom_emoji_id}\">{inner}</tg-emoji>")
+        }
+        _ => inner.to_string(),
+    }
+}
+
+fn wrap_markdown(kind: &MessageEntityKind, raw: &str, inner: &str) -> String {
+    match kind {
+        MessageEntityKind::Bold => markdown::bold(inner),
+        MessageEntityKind::Italic => markdown::italic(inner),
+        MessageEntityKind::Underline => markdown::underline(inner),
+        MessageEntityKind::Strikethrough => markdown::strike(inner),
+        MessageEntityKind::Spoiler => format!("||{inner}||"),
+        MessageEntityKind::Blockquote => inner
+            .split('\n')
+            .map(markdown::blockquote)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        MessageEntityKind::Code => markdown::code_inline(raw),
+        MessageEntityKind::Pre {
+            language: Some(language),
+        } => markdown::code_block_with_lang(raw, language),
+        MessageEntityKind::Pre { language: None } => markdown::code_block(raw),
+        MessageEntityKind::TextLink { url } => markdown::link(url.as_str(), inner),
+        MessageEntityKind::TextMention { user } => markdown::user_mention(user.id, inner),
+        MessageEntityKind::CustomEmoji { custom_emoji_id } => {
+            format!("![{inner}](tg://emoji?id={custom_emoji_id})")
+        }
+        _ => inner.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bold(offset: usize, length: usize) -> MessageEntity {
+        MessageEntity::new(MessageEntityKind::Bold, offset, length)
+    }
+
+    fn italic(offset: usize, length: usize) -> MessageEntity {
+        MessageEntity::new(MessageEntityKind::Italic, offset, length)
+    }
+
+    #[test]
+    fn split_caption_at_separator_line() {
+        assert_eq!(
+            split_caption("Cute cat\n//\nforce #daily"),
+            Some(("Cute cat", "force #daily"))
+        );
+        assert_eq!(
+            split_caption("Cute cat  \n  //  \n"),
+            Some(("Cute cat", ""))
+        );
+        assert_eq!(split_caption("//\nforce"), Some(("", "force")));
+    }
+
+    #[test]
+    fn split_caption_needs_separator_on_its_own_line() {
+        assert_eq!(split_caption("Cute cat force"), None);
+        assert_eq!(split_caption("see https://example.com"), None);
+        assert_eq!(split_caption("a // b"), None);
+    }
+
+    #[test]
+    fn split_text_without_separator_is_all_body() {
+        assert_eq!(split_text("Hello"), ("Hello", ""));
+        assert_eq!(split_text("Hello\n//\n#news"), ("Hello", "#news"));
+    }
+
+    #[test]
+    fn clip_entities_counts_utf16() {
+        // "😀 " takes three UTF-16 code units, so the prefix is 7 units long.
+        let text = "😀 bold";
+        let entities = [bold(3, 4), italic(3, 10), bold(8, 2)];
+        assert_eq!(clip_entities(text, &entities), [bold(3, 4), italic(3, 4)]);
+    }
+
+    #[test]
+    fn suffix_entities_rebase_onto_arguments() {
+        let text = "/recaption 😀 new";
+        let entities = [bold(0, 10), italic(14, 3)];
+        assert_eq!(suffix_entities(text, &entities, "😀 new"), [italic(3, 3)]);
+        assert_eq!(suffix_entities(text, &entities, "other"), []);
+    }
+
+    #[test]
+    fn strip_keywords_around_text() {
+        let is_keyword = |word: &str| word == "force" || word == "#daily";
+
+        let (text, entities) = strip_keywords("#daily 😀 cat\nforce", &[bold(7, 6)], is_keyword);
+        assert_eq!(text, "😀 cat");
+        assert_eq!(entities, [bold(0, 6)]);
+
+        let (text, _) = strip_keywords("May the force be with you", &[], is_keyword);
+        assert_eq!(text, "May the force be with you");
+
+        assert_eq!(
+            strip_keywords(" force  #daily ", &[bold(1, 5)], is_keyword),
+            ("", vec![])
+        );
+    }
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        let (text, entities) = truncate("short", &[bold(0, 5)], 5);
+        assert_eq!(text, "short");
+        assert_eq!(entities, [bold(0, 5)]);
+    }
+
+    #[test]
+    fn truncate_counts_utf16_and_clips_entities() {
+        // Each emoji takes two UTF-16 code units.
+        let (text, entities) = truncate("😀😀 bold text", &[bold(5, 9)], 8);
+        assert_eq!(text, "😀😀 bo…");
+        assert_eq!(entities, [bold(5, 2)]);
+        assert!(telegram_len(&text) <= 8);
+    }
+
+    #[test]
+    fn truncate_to_nothing() {
+        assert_eq!(truncate("text", &[bold(0, 4)], 0), (String::new(), vec![]));
+    }
+}
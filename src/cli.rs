@@ -1,6 +1,17 @@
-use crate::config::Config;
+use crate::{
+    captions::CaptionTemplates,
+    config::{Config, DocumentImages, SenderMode},
+    database::QueueOrder,
+    scheduling::{AdaptivePacing, PostingWindows, PublicationSlots, SilentHours},
+};
+use chrono_tz::Tz;
 use clap::{ArgAction, Command, arg, value_parser};
 use std::time::Duration;
+use teloxide::types::ParseMode;
+use uuid::Uuid;
+
+/// Shortest lease TTL, as leases are renewed every third of it.
+const MIN_LEASE_TTL: Duration = Duration::from_secs(5);
 
 pub fn parse_args() -> Config {
     let matches = Command::new("channel-helper-rs")
@@ -44,7 +55,14 @@ pub fn parse_args() -> Config {
                 .id("interval")
                 .env("INTERVAL")
                 .value_parser(humantime::parse_duration)
-                .required(true),
+                .required_unless_present("schedule"),
+        )
+        .arg(
+            arg!(--schedule <SCHEDULE>)
+                .id("schedule")
+                .env("SCHEDULE")
+                .value_parser(|s: &str| s.parse::<PublicationSlots>())
+                .required(false),
         )
         .arg(
             arg!(-g - -"group-threshold")
@@ -54,6 +72,190 @@ pub fn parse_args() -> Config {
                 .value_parser(value_parser!(i64))
                 .required(false),
         )
+        .arg(
+            arg!(--"group-min" <GROUP_MIN_SIZE>)
+                .id("group_min_size")
+                .env("GROUP_MIN_SIZE")
+                .value_parser(value_parser!(i64).range(2..=10))
+                .default_value("2"),
+        )
+        .arg(
+            arg!(--"group-max" <GROUP_MAX_SIZE>)
+                .id("group_max_size")
+                .env("GROUP_MAX_SIZE")
+                .value_parser(value_parser!(i64).range(2..=10))
+                .default_value("10"),
+        )
+        .arg(
+            arg!(-o --"queue-order" <QUEUE_ORDER>)
+                .id("queue_order")
+                .env("QUEUE_ORDER")
+                .value_parser(|s: &str| s.parse::<QueueOrder>())
+                .default_value("random"),
+        )
+        .arg(
+            arg!(--"max-attempts" <MAX_ATTEMPTS>)
+                .id("max_attempts")
+                .env("MAX_ATTEMPTS")
+                .value_parser(value_parser!(i32).range(1..))
+                .default_value("5"),
+        )
+        .arg(
+            arg!(--"retry-backoff" <RETRY_BACKOFF>)
+                .id("retry_backoff")
+                .env("RETRY_BACKOFF")
+                .value_parser(humantime::parse_duration)
+                .default_value("5m"),
+        )
+        .arg(
+            arg!(-z --timezone <TIMEZONE>)
+                .id("timezone")
+                .env("TIMEZONE")
+                .value_parser(|s: &str| s.parse::<Tz>())
+                .default_value("UTC"),
+        )
+        .arg(
+            arg!(--"posting-windows" <POSTING_WINDOWS>)
+                .id("posting_windows")
+                .env("POSTING_WINDOWS")
+                .value_parser(|s: &str| s.parse::<PostingWindows>())
+                .required(false),
+        )
+        .arg(
+            arg!(--"spread-interval")
+                .id("spread_interval")
+                .env("SPREAD_INTERVAL")
+                .action(ArgAction::SetTrue)
+                .required(false)
+                .requires_if("true", "posting_windows"),
+        )
+        .arg(
+            arg!(--"silent-hours" <SILENT_HOURS>)
+                .id("silent_hours")
+                .env("SILENT_HOURS")
+                .value_parser(|s: &str| s.parse::<SilentHours>())
+                .required(false),
+        )
+        .arg(
+            arg!(--"protect-content")
+                .id("protect_content")
+                .env("PROTECT_CONTENT")
+                .action(ArgAction::SetTrue)
+                .required(false),
+        )
+        .arg(
+            arg!(--runway <RUNWAY>)
+                .id("runway")
+                .env("RUNWAY")
+                .value_parser(humantime::parse_duration)
+                .required(false),
+        )
+        .arg(
+            arg!(--"min-interval" <MIN_INTERVAL>)
+                .id("min_interval")
+                .env("MIN_INTERVAL")
+                .value_parser(humantime::parse_duration)
+                .default_value("10m"),
+        )
+        .arg(
+            arg!(--"max-interval" <MAX_INTERVAL>)
+                .id("max_interval")
+                .env("MAX_INTERVAL")
+                .value_parser(humantime::parse_duration)
+                .default_value("1d"),
+        )
+        .arg(
+            arg!(--"sender-mode" <SENDER_MODE>)
+                .id("sender_mode")
+                .env("SENDER_MODE")
+                .value_parser(|s: &str| s.parse::<SenderMode>())
+                // Lets `--preview-chat` be required however the mode is spelled.
+                .ignore_case(true)
+                .default_value("live"),
+        )
+        .arg(
+            arg!(--"preview-chat" <PREVIEW_CHAT_ID>)
+                .id("preview_chat_id")
+                .env("PREVIEW_CHAT_ID")
+                .value_parser(value_parser!(i64))
+                .required_if_eq("sender_mode", "preview"),
+        )
+        .arg(
+            arg!(--"instance-id" <INSTANCE_ID>)
+                .id("instance_id")
+                .env("INSTANCE_ID")
+                .required(false),
+        )
+        .arg(
+            arg!(--"lease-ttl" <LEASE_TTL>)
+                .id("lease_ttl")
+                .env("LEASE_TTL")
+                .value_parser(|s: &str| match humantime::parse_duration(s) {
+                    Ok(ttl) if ttl < MIN_LEASE_TTL => Err(format!(
+                        "lease TTL must be at least {}",
+                        humantime::format_duration(MIN_LEASE_TTL)
+                    )),
+                    Ok(ttl) => Ok(ttl),
+                    Err(e) => Err(e.to_string()),
+                })
+                .default_value("30s"),
+        )
+        .arg(
+            arg!(--"document-images" <DOCUMENT_IMAGES>)
+                .id("document_images")
+                .env("DOCUMENT_IMAGES")
+                .value_parser(|s: &str| s.parse::<DocumentImages>())
+                .default_value("photo"),
+        )
+        .arg(
+            arg!(--"caption-template" <CAPTION_TEMPLATE>)
+                .id("caption_template")
+                .env("CAPTION_TEMPLATE")
+                .required(false),
+        )
+        .arg(
+            arg!(--"caption-template-photo" <CAPTION_TEMPLATE_PHOTO>)
+                .id("caption_template_photo")
+                .env("CAPTION_TEMPLATE_PHOTO")
+                .required(false),
+        )
+        .arg(
+            arg!(--"caption-template-video" <CAPTION_TEMPLATE_VIDEO>)
+                .id("caption_template_video")
+                .env("CAPTION_TEMPLATE_VIDEO")
+                .required(false),
+        )
+        .arg(
+            arg!(--"caption-template-animation" <CAPTION_TEMPLATE_ANIMATION>)
+                .id("caption_template_animation")
+                .env("CAPTION_TEMPLATE_ANIMATION")
+                .required(false),
+        )
+        .arg(
+            arg!(--"caption-parse-mode" <CAPTION_PARSE_MODE>)
+                .id("caption_parse_mode")
+                .env("CAPTION_PARSE_MODE")
+                .value_parser(|s: &str| match s.to_lowercase().as_str() {
+                    "html" => Ok(ParseMode::Html),
+                    "markdownv2" => Ok(ParseMode::MarkdownV2),
+                    _ => Err(format!(
+                        "unknown parse mode {s:?}, expected html or markdownv2"
+                    )),
+                })
+                .default_value("html"),
+        )
+        .arg(
+            arg!(--signature <CHANNEL_SIGNATURE>)
+                .id("channel_signature")
+                .env("CHANNEL_SIGNATURE")
+                .required(false),
+        )
+        .arg(
+            arg!(--link <CHANNEL_LINK>)
+                .id("channel_link")
+                .env("CHANNEL_LINK")
+                .required(false),
+        )
         .arg(
             arg!(-w - -api)
                 .id("with_api")
@@ -87,21 +289,85 @@ pub fn parse_args() -> Config {
         .unwrap()
         .map(|v| v.parse().unwrap())
         .collect();
-    let interval = matches.get_one::<Duration>("interval").unwrap();
+    let interval = matches.get_one::<Duration>("interval");
+    let schedule = matches.get_one::<PublicationSlots>("schedule");
     let group_threshold = matches.get_one::<i64>("group_threshold");
+    let group_min_size = matches.get_one::<i64>("group_min_size").unwrap();
+    let group_max_size = matches.get_one::<i64>("group_max_size").unwrap();
+    let queue_order = matches.get_one::<QueueOrder>("queue_order").unwrap();
+    let max_attempts = matches.get_one::<i32>("max_attempts").unwrap();
+    let retry_backoff = matches.get_one::<Duration>("retry_backoff").unwrap();
+    let timezone = matches.get_one::<Tz>("timezone").unwrap();
+    let posting_windows = matches.get_one::<PostingWindows>("posting_windows");
+    let spread_interval = matches.get_one::<bool>("spread_interval").unwrap();
+    let silent_hours = matches.get_one::<SilentHours>("silent_hours");
+    let protect_content = matches.get_one::<bool>("protect_content").unwrap();
+    let runway = matches.get_one::<Duration>("runway");
+    let min_interval = matches.get_one::<Duration>("min_interval").unwrap();
+    let max_interval = matches.get_one::<Duration>("max_interval").unwrap();
+    let sender_mode = matches.get_one::<SenderMode>("sender_mode").unwrap();
+    let preview_chat_id = matches.get_one::<i64>("preview_chat_id");
+    let instance_id = matches
+        .get_one::<String>("instance_id")
+        .cloned()
+        .unwrap_or_else(|| Uuid::now_v7().to_string());
+    let lease_ttl = matches.get_one::<Duration>("lease_ttl").unwrap();
+    let document_images = matches
+        .get_one::<DocumentImages>("document_images")
+        .unwrap();
+    // Templates usually come from env files, so allow `\n` for line breaks.
+    let template = |id: &str| {
+        matches
+            .get_one::<String>(id)
+            .map(|v| v.replace("\\n", "\n"))
+    };
+    let caption_templates = CaptionTemplates {
+        default: template("caption_template"),
+        photo: template("caption_template_photo"),
+        video: template("caption_template_video"),
+        animation: template("caption_template_animation"),
+    };
+    let caption_parse_mode = matches.get_one::<ParseMode>("caption_parse_mode").unwrap();
+    let channel_signature = matches.get_one::<String>("channel_signature");
+    let channel_link = matches.get_one::<String>("channel_link");
     let with_api = matches.get_one::<bool>("with_api").unwrap();
     let api_port = matches.get_one::<u16>("api_port");
     let upload_chat_id = matches.get_one::<i64>("upload_chat_id");
     Config {
         bot_token: bot_token.clone(),
         db_name: db_name.clone(),
-        target_chat_id: target_chat_id.clone(),
+        target_chat_id: *target_chat_id,
         // admin_id: admin_id.clone(),
         allowed_sender_chats,
-        interval: interval.clone(),
-        group_threshold: group_threshold.map(|v| *v).unwrap_or(0),
-        with_api: with_api.clone(),
-        api_port: api_port.map(|v| *v).clone(),
-        upload_chat_id: upload_chat_id.map(|v| *v).clone(),
+        interval: interval.copied(),
+        schedule: schedule.cloned(),
+        group_threshold: group_threshold.copied().unwrap_or(0),
+        group_min_size: *group_min_size,
+        group_max_size: (*group_max_size).max(*group_min_size),
+        queue_order: *queue_order,
+        max_attempts: *max_attempts,
+        retry_backoff: *retry_backoff,
+        timezone: *timezone,
+        posting_windows: posting_windows.cloned(),
+        spread_interval: *spread_interval,
+        silent_hours: silent_hours.cloned(),
+        protect_content: *protect_content,
+        adaptive: runway.map(|runway| AdaptivePacing {
+            runway: *runway,
+            min_interval: *min_interval,
+            max_interval: (*max_interval).max(*min_interval),
+        }),
+        sender_mode: *sender_mode,
+        preview_chat_id: preview_chat_id.copied(),
+        instance_id,
+        lease_ttl: *lease_ttl,
+        document_images: *document_images,
+        caption_templates,
+        caption_parse_mode: *caption_parse_mode,
+        channel_signature: channel_signature.cloned(),
+        channel_link: channel_link.cloned(),
+        with_api: *with_api,
+        api_port: api_port.copied(),
+        upload_chat_id: upload_chat_id.copied(),
     }
 }
@@ -1,4 +1,11 @@
+use crate::captions::CaptionTemplates;
+use crate::database::QueueOrder;
+use crate::scheduling::{AdaptivePacing, PostingWindows, PublicationSlots, SilentHours};
+use chrono_tz::Tz;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::Duration;
+use teloxide::types::ParseMode;
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,9 +14,86 @@ pub struct Config {
     pub target_chat_id: i64,
     // pub admin_id: i64,
     pub allowed_sender_chats: Vec<i64>,
-    pub interval: Duration,
+    pub interval: Option<Duration>,
+    pub schedule: Option<PublicationSlots>,
     pub group_threshold: i64,
+    pub group_min_size: i64,
+    pub group_max_size: i64,
+    pub queue_order: QueueOrder,
+    pub max_attempts: i32,
+    pub retry_backoff: Duration,
+    pub timezone: Tz,
+    pub posting_windows: Option<PostingWindows>,
+    pub spread_interval: bool,
+    pub silent_hours: Option<SilentHours>,
+    pub protect_content: bool,
+    pub adaptive: Option<AdaptivePacing>,
+    pub sender_mode: SenderMode,
+    pub preview_chat_id: Option<i64>,
+    pub instance_id: String,
+    pub lease_ttl: Duration,
+    pub document_images: DocumentImages,
+    pub caption_templates: CaptionTemplates,
+    pub caption_parse_mode: ParseMode,
+    pub channel_signature: Option<String>,
+    pub channel_link: Option<String>,
     pub with_api: bool,
     pub api_port: Option<u16>,
     pub upload_chat_id: Option<i64>,
 }
+
+/// What the sender does with the posts it picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderMode {
+    /// Publish to the targets and mark posts as sent.
+    Live,
+    /// Only log what would be published.
+    DryRun,
+    /// Publish to the preview chat instead of the targets, leaving posts
+    /// unsent.
+    Preview,
+}
+
+impl FromStr for SenderMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "live" => Ok(SenderMode::Live),
+            "dry-run" | "dryrun" => Ok(SenderMode::DryRun),
+            "preview" => Ok(SenderMode::Preview),
+            _ => anyhow::bail!("unknown sender mode {s:?}, expected live, dry-run or preview"),
+        }
+    }
+}
+
+impl Display for SenderMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SenderMode::Live => "live",
+            SenderMode::DryRun => "dry-run",
+            SenderMode::Preview => "preview",
+        })
+    }
+}
+
+/// How images submitted as uncompressed documents are queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentImages {
+    /// Convert to a regular photo, downscaling oversized images.
+    Photo,
+    /// Publish the original file as a document.
+    Document,
+}
+
+impl FromStr for DocumentImages {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "photo" => Ok(DocumentImages::Photo),
+            "document" => Ok(DocumentImages::Document),
+            _ => anyhow::bail!("unknown document image mode {s:?}, expected photo or document"),
+        }
+    }
+}
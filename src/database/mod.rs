@@ -1,7 +1,11 @@
-use chrono::Utc;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::connection::SimpleConnection;
 use diesel::dsl::exists;
 use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use tokio::sync::{Mutex, Notify};
 use uuid::Uuid;
 
@@ -9,49 +13,142 @@ mod models;
 mod schema;
 
 use crate::database::models::UUID;
-pub use models::{MediaType, Post, PostMessageId, UploadTask};
+pub use models::{
+    Lease, MediaType, NewPost, NewPostItem, PRIORITY_HIGH, PRIORITY_LOW, PRIORITY_NEXT,
+    PRIORITY_NORMAL, Pause, Post, PostItem, PostMessageId, QueueOrder, Target, UploadTask,
+};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 define_sql_function!(fn random() -> Text);
 
+pub const QUEUE_ORDER_SETTING: &str = "queue_order";
+pub const DUPLICATES_CAUGHT_SETTING: &str = "duplicates_caught";
+pub const PAUSED_UNTIL_SETTING: &str = "paused_until";
+
+/// Lease a live sender must hold to publish, so that only one of the
+/// instances sharing a database sends posts.
+pub const SENDER_LEASE: &str = "sender";
+
+/// Lease an instance must hold to poll for updates, as Telegram rejects
+/// concurrent polling with the same bot token.
+pub const BOT_LEASE: &str = "bot";
+
+/// How long a connection waits for another process to release the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Target seeded by the migrations and synced from the command line on startup.
+pub const DEFAULT_TARGET_ID: i32 = 1;
+
 pub struct Database {
     conn: Mutex<SqliteConnection>,
     pub upload_task_added: Notify,
+    pub targets_changed: Notify,
+    pub schedule_changed: Notify,
+    pub pause_changed: Notify,
+    /// Posts published by a dry-run or preview sender, which leaves them
+    /// unsent in the database but should not pick them again.
+    simulated_sent: Mutex<HashSet<Uuid>>,
+    /// Failed attempts of a dry-run or preview sender per post, with the time
+    /// the post may be picked again.
+    simulated_failures: Mutex<HashMap<Uuid, (i32, DateTime<Utc>)>>,
+    /// Sender state a dry-run or preview sender keeps in memory instead of
+    /// the settings table, `None` while the sender is live.
+    simulated_settings: std::sync::Mutex<Option<HashMap<String, String>>>,
+    /// Posts being delivered right now, see [`Database::claim_posts`].
+    in_flight: std::sync::Mutex<HashSet<Uuid>>,
+}
+
+/// Delivery claim on posts, released when dropped.
+pub struct PostClaim<'a> {
+    db: &'a Database,
+    ids: Vec<Uuid>,
+}
+
+impl Drop for PostClaim<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.db.in_flight.lock().unwrap();
+        for post_id in &self.ids {
+            in_flight.remove(post_id);
+        }
+    }
 }
 
 impl Database {
     pub fn open(db_name: &str) -> anyhow::Result<Self> {
         let mut conn = SqliteConnection::establish(db_name)?;
+        conn.batch_execute(&format!(
+            "PRAGMA busy_timeout = {};",
+            BUSY_TIMEOUT.as_millis()
+        ))?;
         conn.run_pending_migrations(MIGRATIONS)
             .expect("Unable to apply migrations");
 
         Ok(Self {
             conn: Mutex::new(conn),
             upload_task_added: Notify::new(),
+            targets_changed: Notify::new(),
+            schedule_changed: Notify::new(),
+            pause_changed: Notify::new(),
+            simulated_sent: Mutex::new(HashSet::new()),
+            simulated_failures: Mutex::new(HashMap::new()),
+            simulated_settings: std::sync::Mutex::new(None),
+            in_flight: std::sync::Mutex::new(HashSet::new()),
         })
     }
 
     pub async fn create_post(
         &self,
-        id: Option<Uuid>,
-        media_type: MediaType,
-        file_id: String,
-        image_hash: Option<String>,
+        new_post: NewPost,
         chat_id: i64,
         message_id: i32,
     ) -> anyhow::Result<Post> {
-        use crate::database::schema::{post_message_ids, posts};
+        use crate::database::schema::{post_items, post_message_ids, posts};
+
+        let post_id = new_post.id.unwrap_or(Uuid::now_v7());
 
-        let post_id = id.unwrap_or(Uuid::now_v7());
+        let new_items: Vec<PostItem> = new_post
+            .items
+            .into_iter()
+            .enumerate()
+            .map(|(position, item)| PostItem {
+                post_id,
+                position: position as i32,
+                media_type: item.media_type,
+                file_id: item.file_id,
+                image_hash: item.image_hash,
+                spoiler: item.spoiler,
+                file_unique_id: item.file_unique_id,
+                clip_fingerprint: item.clip_fingerprint,
+            })
+            .collect();
 
         let new_post = Post {
             id: post_id,
-            media_type,
-            file_id,
+            media_type: new_post.media_type,
+            file_id: new_post.file_id,
             is_sent: false,
             created_datetime: Utc::now().naive_utc(),
             sent_datetime: None,
-            image_hash,
+            image_hash: new_post.image_hash,
+            target_id: new_post.target_id,
+            priority: PRIORITY_NORMAL,
+            scheduled_at: None,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: None,
+            failed: false,
+            caption_template: None,
+            contributor: new_post.contributor,
+            source_url: new_post.source_url,
+            tags: new_post.tags,
+            caption: new_post.caption,
+            caption_entities: new_post.caption_entities,
+            unpublished: false,
+            spoiler: new_post.spoiler,
+            silent: None,
+            protect_content: None,
+            file_unique_id: new_post.file_unique_id,
+            clip_fingerprint: new_post.clip_fingerprint,
         };
 
         let new_message_id = PostMessageId {
@@ -67,6 +164,11 @@ impl Database {
                 .get_result(conn)
                 .expect("error saving new post");
 
+            diesel::insert_into(post_items::table)
+                .values(new_items)
+                .execute(conn)
+                .expect("error saving post items");
+
             diesel::insert_into(post_message_ids::table)
                 .values(new_message_id)
                 .execute(conn)
@@ -76,12 +178,25 @@ impl Database {
         })
     }
 
-    pub async fn get_post_by_hash(&self, hash: String) -> anyhow::Result<Option<Post>> {
-        use crate::database::schema::posts::dsl::{deleted, image_hash, posts};
+    /// Finds a post of `target` with an image of the given hash, including
+    /// album items. Each target keeps its own queue, so the same image may be
+    /// queued once per target.
+    pub async fn get_post_by_hash(
+        &self,
+        hash: String,
+        target: i32,
+    ) -> anyhow::Result<Option<Post>> {
+        use crate::database::schema::post_items;
+        use crate::database::schema::posts::dsl::{deleted, id, image_hash, posts, target_id};
 
         self.conn.lock().await.transaction(|conn| {
+            let album_ids = post_items::table
+                .filter(post_items::image_hash.eq(hash.clone()))
+                .select(post_items::post_id);
             Ok(posts
-                .filter(image_hash.eq(hash).and(deleted.eq(false)))
+                .filter(image_hash.eq(hash).or(id.eq_any(album_ids)))
+                .filter(target_id.eq(target))
+                .filter(deleted.eq(false))
                 .limit(1)
                 .select(Post::as_select())
                 .load(conn)
@@ -90,6 +205,58 @@ impl Database {
         })
     }
 
+    /// Finds a post of `target` with the same file, or a video or animation
+    /// with the same fingerprint, including album items.
+    pub async fn get_clip_post(
+        &self,
+        unique_id: String,
+        fingerprint: Option<String>,
+        target: i32,
+    ) -> anyhow::Result<Option<Post>> {
+        use crate::database::schema::post_items;
+        use crate::database::schema::posts::dsl::{
+            clip_fingerprint, deleted, file_unique_id, id, posts, target_id,
+        };
+
+        self.conn.lock().await.transaction(|conn| {
+            let album_ids = post_items::table
+                .filter(
+                    post_items::file_unique_id
+                        .eq(unique_id.clone())
+                        .or(post_items::clip_fingerprint.eq(fingerprint.clone())),
+                )
+                .select(post_items::post_id);
+            Ok(posts
+                .filter(
+                    file_unique_id
+                        .eq(unique_id)
+                        .or(clip_fingerprint.eq(fingerprint))
+                        .or(id.eq_any(album_ids)),
+                )
+                .filter(target_id.eq(target))
+                .filter(deleted.eq(false))
+                .limit(1)
+                .select(Post::as_select())
+                .load(conn)
+                .expect("error fetching post")
+                .pop())
+        })
+    }
+
+    /// Media of an album post, in order.
+    pub async fn fetch_post_items(&self, album_id: Uuid) -> anyhow::Result<Vec<PostItem>> {
+        use crate::database::schema::post_items::dsl::{position, post_id, post_items};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(post_items
+                .filter(post_id.eq(UUID(album_id)))
+                .order(position)
+                .select(PostItem::as_select())
+                .load(conn)
+                .expect("error fetching post items"))
+        })
+    }
+
     pub async fn add_message_id_for_post(
         &self,
         post_id: Uuid,
@@ -184,52 +351,189 @@ impl Database {
         })
     }
 
-    pub async fn unsent_posts_count(&self) -> anyhow::Result<i64> {
-        use crate::database::schema::posts::dsl::{deleted, is_sent, posts};
+    pub async fn mark_simulated_sent<T>(&self, ids: T)
+    where
+        T: IntoIterator<Item = Uuid>,
+    {
+        self.simulated_sent.lock().await.extend(ids);
+    }
+
+    /// Records a failed send of a simulated sender like
+    /// [`Database::record_send_failure`] does, but only in memory. Posts that
+    /// reach `max_attempts` are left out for the lifetime of the process.
+    pub async fn record_simulated_failure<T>(&self, ids: T, max_attempts: i32, backoff: Duration)
+    where
+        T: IntoIterator<Item = Uuid>,
+    {
+        let mut failures = self.simulated_failures.lock().await;
+        for post_id in ids {
+            let (attempts, retry_at) = failures.entry(post_id).or_insert((0, Utc::now()));
+            *attempts += 1;
+            let delay = backoff.saturating_mul(2u32.saturating_pow(*attempts as u32 - 1));
+            *retry_at = chrono::Duration::from_std(delay)
+                .ok()
+                .and_then(|delay| Utc::now().checked_add_signed(delay))
+                .filter(|_| *attempts < max_attempts)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        }
+    }
+
+    /// Keeps sender state such as fired slots and the shuffle bag in memory
+    /// from now on, see [`Database::set_sender_setting`].
+    pub fn simulate_sender_settings(&self) {
+        self.simulated_settings
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new);
+    }
 
+    /// Fetches sender state, preferring the value a simulated sender recorded.
+    pub async fn get_sender_setting(&self, setting_key: &str) -> anyhow::Result<Option<String>> {
+        let simulated = self
+            .simulated_settings
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|simulated| simulated.get(setting_key).cloned());
+        match simulated {
+            Some(setting_value) => Ok(Some(setting_value)),
+            None => self.get_setting(setting_key).await,
+        }
+    }
+
+    /// Saves sender state, only in memory while the sender is simulated.
+    pub async fn set_sender_setting(
+        &self,
+        setting_key: &str,
+        setting_value: String,
+    ) -> anyhow::Result<()> {
+        if let Some(simulated) = self.simulated_settings.lock().unwrap().as_mut() {
+            simulated.insert(setting_key.to_string(), setting_value);
+            return Ok(());
+        }
+        self.set_setting(setting_key, setting_value).await
+    }
+
+    /// Posts a simulated sender must not pick: those it has already sent and
+    /// those waiting out a failed attempt.
+    async fn simulated_excluded(&self) -> Vec<UUID> {
+        let now = Utc::now();
+        let backed_off: Vec<Uuid> = self
+            .simulated_failures
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, (_, retry_at))| *retry_at > now)
+            .map(|(post_id, _)| *post_id)
+            .collect();
+        self.simulated_sent
+            .lock()
+            .await
+            .iter()
+            .copied()
+            .chain(backed_off)
+            .map(UUID)
+            .collect()
+    }
+
+    pub async fn unsent_posts_count(&self, target: i32) -> anyhow::Result<i64> {
+        let excluded = self.simulated_excluded().await;
         self.conn.lock().await.transaction(|conn| {
-            Ok(posts
-                .filter(is_sent.eq(false).and(deleted.eq(false)))
+            Ok(queued_posts(target, &excluded)
                 .count()
                 .get_result(conn)
                 .expect("error getting unsent posts count"))
         })
     }
 
-    pub async fn fetch_unsent_post(&self) -> anyhow::Result<Option<Post>> {
-        use crate::database::schema::posts::dsl::{deleted, is_sent, posts};
-
+    pub async fn fetch_unsent_post(
+        &self,
+        target: i32,
+        order: QueueOrder,
+    ) -> anyhow::Result<Option<Post>> {
+        let excluded = self.simulated_excluded().await;
         self.conn.lock().await.transaction(|conn| {
-            Ok(posts
-                .filter(is_sent.eq(false).and(deleted.eq(false)))
-                .limit(1)
-                .order_by(random())
-                .select(Post::as_select())
-                .load(conn)
-                .expect("error fetching unsent post")
-                .pop())
+            Ok(order_unsent(
+                conn,
+                &self.simulated_settings,
+                queued_posts(target, &excluded),
+                target,
+                order,
+                &excluded,
+            )
+            .limit(1)
+            .select(Post::as_select())
+            .load(conn)
+            .expect("error fetching unsent post")
+            .pop())
         })
     }
 
-    pub async fn fetch_ten_unsent_photo_posts(&self) -> anyhow::Result<Vec<Post>> {
-        use crate::database::schema::posts::dsl::{deleted, is_sent, media_type, posts};
+    /// Fetches up to `limit` unsent posts that may share a media group.
+    pub async fn fetch_unsent_group_posts(
+        &self,
+        target: i32,
+        order: QueueOrder,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Post>> {
+        use crate::database::schema::posts::dsl::media_type;
 
+        let excluded = self.simulated_excluded().await;
         self.conn.lock().await.transaction(|conn| {
-            Ok(posts
-                .filter(
-                    is_sent
-                        .eq(false)
-                        .and(media_type.eq(MediaType::Photo))
-                        .and(deleted.eq(false)),
-                )
-                .limit(10)
-                .order_by(random())
-                .select(Post::as_select())
-                .load(conn)
-                .expect("error fetching unsent photo posts"))
+            let query =
+                queued_posts(target, &excluded).filter(media_type.eq_any(MediaType::GROUPABLE));
+            Ok(order_unsent(
+                conn,
+                &self.simulated_settings,
+                query,
+                target,
+                order,
+                &excluded,
+            )
+            .limit(limit)
+            .select(Post::as_select())
+            .load(conn)
+            .expect("error fetching unsent group posts"))
         })
     }
 
+    /// Claims posts for delivery, so that the target's sender and `/sendnow`
+    /// cannot publish the same post twice. Returns `None` when any of them is
+    /// already being delivered or no longer waits in the queue.
+    pub async fn claim_posts(&self, ids: &[Uuid]) -> anyhow::Result<Option<PostClaim<'_>>> {
+        use crate::database::schema::posts::dsl::{deleted, id, is_sent, posts};
+
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if ids.iter().any(|post_id| in_flight.contains(post_id)) {
+                return Ok(None);
+            }
+            in_flight.extend(ids.iter().copied());
+        }
+        let claim = PostClaim {
+            db: self,
+            ids: ids.to_vec(),
+        };
+
+        // Checked only after claiming, so a post another sender has just
+        // published is already marked as sent.
+        let excluded = self.simulated_excluded().await;
+        let queued: i64 = self.conn.lock().await.transaction(|conn| {
+            Ok::<_, anyhow::Error>(
+                posts
+                    .filter(id.eq_any(ids.iter().copied().map(UUID)))
+                    .filter(id.ne_all(excluded))
+                    .filter(is_sent.eq(false))
+                    .filter(deleted.eq(false))
+                    .count()
+                    .get_result(conn)
+                    .expect("error checking queued posts"),
+            )
+        })?;
+
+        Ok((queued == ids.len() as i64).then_some(claim))
+    }
+
     pub async fn mark_sent_posts<T>(&self, ids: T) -> anyhow::Result<()>
     where
         T: IntoIterator<Item = Uuid>,
@@ -237,7 +541,7 @@ impl Database {
         use crate::database::schema::posts::dsl::{id, is_sent, posts, sent_datetime};
 
         self.conn.lock().await.transaction(|conn| {
-            diesel::update(posts.filter(id.eq_any(ids.into_iter().map(|v| UUID::from(v)))))
+            diesel::update(posts.filter(id.eq_any(ids.into_iter().map(UUID::from))))
                 .set((is_sent.eq(true), sent_datetime.eq(Utc::now().naive_utc())))
                 .execute(conn)
                 .expect("error marking post as sent");
@@ -245,6 +549,90 @@ impl Database {
         })
     }
 
+    /// Message ids of a post in `chat_id`, e.g. its publications in a channel.
+    pub async fn fetch_post_message_ids(&self, post: Uuid, chat: i64) -> anyhow::Result<Vec<i32>> {
+        use crate::database::schema::post_message_ids::dsl::{
+            chat_id, message_id, post_id, post_message_ids,
+        };
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(post_message_ids
+                .filter(post_id.eq(UUID(post)).and(chat_id.eq(chat)))
+                .select(message_id)
+                .load(conn)
+                .expect("error fetching post message ids"))
+        })
+    }
+
+    /// Marks a sent post as removed from the channel `chat` and forgets its
+    /// message ids there.
+    pub async fn mark_post_unpublished(&self, post: Uuid, chat: i64) -> anyhow::Result<()> {
+        use crate::database::schema::{
+            post_message_ids::dsl::{chat_id, post_id, post_message_ids},
+            posts::dsl::{id, posts, unpublished},
+        };
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post))))
+                .set(unpublished.eq(true))
+                .execute(conn)
+                .expect("error marking post as unpublished");
+            diesel::delete(post_message_ids.filter(post_id.eq(UUID(post)).and(chat_id.eq(chat))))
+                .execute(conn)
+                .expect("error deleting post message ids");
+            Ok(())
+        })
+    }
+
+    /// Puts a sent post back into the queue of its target, forgetting its
+    /// message ids in the channel `chat` so that later actions only touch the
+    /// new publication.
+    pub async fn requeue_sent_post(&self, post: Uuid, chat: i64) -> anyhow::Result<()> {
+        use crate::database::schema::{
+            post_message_ids::dsl::{chat_id, post_id, post_message_ids},
+            posts::dsl::{
+                attempts, failed, id, is_sent, last_error, next_attempt_at, posts, sent_datetime,
+                unpublished,
+            },
+        };
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post))))
+                .set((
+                    is_sent.eq(false),
+                    sent_datetime.eq(None::<NaiveDateTime>),
+                    unpublished.eq(false),
+                    attempts.eq(0),
+                    last_error.eq(None::<String>),
+                    next_attempt_at.eq(None::<NaiveDateTime>),
+                    failed.eq(false),
+                ))
+                .execute(conn)
+                .expect("error requeueing sent post");
+            diesel::delete(post_message_ids.filter(post_id.eq(UUID(post)).and(chat_id.eq(chat))))
+                .execute(conn)
+                .expect("error deleting post message ids");
+            Ok(())
+        })
+    }
+
+    pub async fn set_post_caption(
+        &self,
+        post_id: Uuid,
+        text: Option<String>,
+        entities: Option<String>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{caption, caption_entities, id, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set((caption.eq(text), caption_entities.eq(entities)))
+                .execute(conn)
+                .expect("error updating post caption");
+            Ok(())
+        })
+    }
+
     pub async fn fetch_post_by_message_id(
         &self,
         chat_id: i64,
@@ -274,6 +662,19 @@ impl Database {
         })
     }
 
+    pub async fn fetch_post(&self, post_id: Uuid) -> anyhow::Result<Option<Post>> {
+        use crate::database::schema::posts::dsl::posts;
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .find(UUID(post_id))
+                .select(Post::as_select())
+                .first(conn)
+                .optional()
+                .expect("error fetching post"))
+        })
+    }
+
     pub async fn delete_post(&self, post_id: Uuid) -> anyhow::Result<()> {
         use crate::database::schema::posts::dsl::{deleted, id, posts};
 
@@ -285,4 +686,706 @@ impl Database {
             Ok(())
         })
     }
+
+    pub async fn restore_post(&self, post_id: Uuid) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{deleted, id, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(deleted.eq(false))
+                .execute(conn)
+                .expect("error restoring post");
+            Ok(())
+        })
+    }
+
+    pub async fn get_setting(&self, setting_key: &str) -> anyhow::Result<Option<String>> {
+        use crate::database::schema::settings::dsl::{settings, value};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(settings
+                .find(setting_key)
+                .select(value)
+                .first(conn)
+                .optional()
+                .expect("error fetching setting"))
+        })
+    }
+
+    pub async fn set_setting(
+        &self,
+        setting_key: &str,
+        setting_value: String,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::settings::dsl::{key, settings, value};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::insert_into(settings)
+                .values((key.eq(setting_key), value.eq(&setting_value)))
+                .on_conflict(key)
+                .do_update()
+                .set(value.eq(&setting_value))
+                .execute(conn)
+                .expect("error saving setting");
+            Ok(())
+        })
+    }
+
+    pub async fn delete_setting(&self, setting_key: &str) -> anyhow::Result<()> {
+        use crate::database::schema::settings::dsl::settings;
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::delete(settings.find(setting_key))
+                .execute(conn)
+                .expect("error deleting setting");
+            Ok(())
+        })
+    }
+
+    /// Returns the current pause, resuming publishing once it has expired.
+    pub async fn pause(&self) -> anyhow::Result<Option<Pause>> {
+        match self.get_setting(PAUSED_UNTIL_SETTING).await? {
+            Some(value) => match value.parse::<Pause>() {
+                Ok(Pause::Until(until)) if until <= Utc::now() => {
+                    self.set_pause(None).await?;
+                    Ok(None)
+                }
+                Ok(pause) => Ok(Some(pause)),
+                Err(e) => {
+                    log::error!("invalid pause setting {value:?}: {e:?}");
+                    Ok(None)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Pauses publishing, or resumes it with `None`.
+    pub async fn set_pause(&self, pause: Option<Pause>) -> anyhow::Result<()> {
+        match pause {
+            Some(pause) => {
+                self.set_setting(PAUSED_UNTIL_SETTING, pause.to_string())
+                    .await?
+            }
+            None => self.delete_setting(PAUSED_UNTIL_SETTING).await?,
+        }
+        self.pause_changed.notify_waiters();
+        Ok(())
+    }
+
+    /// Takes or renews the lease `lease_name` for `holder` for `ttl`, unless
+    /// another holder's lease has not expired yet. Returns the lease as it
+    /// stands afterwards, so the caller holds it if the holder matches.
+    pub async fn acquire_lease(
+        &self,
+        lease_name: &str,
+        lease_holder: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<Lease> {
+        use crate::database::schema::leases::dsl::leases;
+        let now = Utc::now().naive_utc();
+        let lease = Lease {
+            name: lease_name.to_string(),
+            holder: lease_holder.to_string(),
+            heartbeat_at: now,
+            expires_at: now + ttl,
+        };
+
+        // Takes the write lock up front, so that instances racing for an
+        // expired lease cannot both win it.
+        self.conn.lock().await.immediate_transaction(|conn| {
+            let current: Option<Lease> = leases.find(lease_name).first(conn).optional()?;
+            match current {
+                Some(current) if current.holder != lease_holder && current.expires_at > now => {
+                    Ok(current)
+                }
+                _ => {
+                    diesel::replace_into(leases).values(&lease).execute(conn)?;
+                    Ok(lease)
+                }
+            }
+        })
+    }
+
+    /// Whether `holder` currently holds the unexpired lease `lease_name`.
+    pub async fn holds_lease(&self, lease_name: &str, lease_holder: &str) -> anyhow::Result<bool> {
+        use crate::database::schema::leases::dsl::{expires_at, holder, leases};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(diesel::select(exists(
+                leases
+                    .find(lease_name)
+                    .filter(holder.eq(lease_holder))
+                    .filter(expires_at.gt(Utc::now().naive_utc())),
+            ))
+            .get_result(conn)?)
+        })
+    }
+
+    /// Gives up the lease `lease_name` if `holder` still holds it.
+    pub async fn release_lease(&self, lease_name: &str, lease_holder: &str) -> anyhow::Result<()> {
+        use crate::database::schema::leases::dsl::{holder, leases};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::delete(leases.find(lease_name).filter(holder.eq(lease_holder)))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Increments a numeric setting, returning the new value.
+    pub async fn increment_setting(&self, setting_key: &str) -> anyhow::Result<i64> {
+        use crate::database::schema::settings::dsl::{key, settings, value};
+
+        self.conn.lock().await.transaction(|conn| {
+            let current: i64 = settings
+                .find(setting_key)
+                .select(value)
+                .first::<String>(conn)
+                .optional()
+                .expect("error fetching setting")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            let new_value = (current + 1).to_string();
+            diesel::insert_into(settings)
+                .values((key.eq(setting_key), value.eq(&new_value)))
+                .on_conflict(key)
+                .do_update()
+                .set(value.eq(&new_value))
+                .execute(conn)
+                .expect("error saving setting");
+            Ok(current + 1)
+        })
+    }
+
+    /// Unsent posts across all targets, by media type.
+    pub async fn unsent_counts_by_media_type(&self) -> anyhow::Result<Vec<(MediaType, i64)>> {
+        use crate::database::schema::posts::dsl::{deleted, failed, is_sent, media_type, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(
+                    is_sent
+                        .eq(false)
+                        .and(deleted.eq(false))
+                        .and(failed.eq(false)),
+                )
+                .group_by(media_type)
+                .select((media_type, diesel::dsl::count_star()))
+                .order_by(media_type)
+                .load(conn)
+                .expect("error counting unsent posts by media type"))
+        })
+    }
+
+    pub async fn sent_posts_count_since(&self, since: NaiveDateTime) -> anyhow::Result<i64> {
+        use crate::database::schema::posts::dsl::{is_sent, posts, sent_datetime};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(is_sent.eq(true).and(sent_datetime.ge(since)))
+                .count()
+                .get_result(conn)
+                .expect("error counting sent posts"))
+        })
+    }
+
+    pub async fn deleted_posts_count(&self) -> anyhow::Result<i64> {
+        use crate::database::schema::posts::dsl::{deleted, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(deleted.eq(true))
+                .count()
+                .get_result(conn)
+                .expect("error counting deleted posts"))
+        })
+    }
+
+    pub async fn last_sent_datetime(&self, target: i32) -> anyhow::Result<Option<NaiveDateTime>> {
+        use crate::database::schema::posts::dsl::{is_sent, posts, sent_datetime, target_id};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(is_sent.eq(true).and(target_id.eq(target)))
+                .select(diesel::dsl::max(sent_datetime))
+                .first(conn)
+                .expect("error fetching last sent datetime"))
+        })
+    }
+
+    pub async fn set_post_schedule(
+        &self,
+        post_id: Uuid,
+        value: Option<NaiveDateTime>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{id, posts, scheduled_at};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(scheduled_at.eq(value))
+                .execute(conn)
+                .expect("error updating post schedule");
+            Ok::<_, anyhow::Error>(())
+        })?;
+        self.schedule_changed.notify_one();
+        Ok(())
+    }
+
+    pub async fn fetch_next_scheduled_post(&self) -> anyhow::Result<Option<Post>> {
+        use crate::database::schema::posts::dsl::{
+            deleted, failed, id, is_sent, posts, scheduled_at,
+        };
+
+        let excluded = self.simulated_excluded().await;
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(
+                    is_sent
+                        .eq(false)
+                        .and(deleted.eq(false))
+                        .and(failed.eq(false))
+                        .and(scheduled_at.is_not_null())
+                        .and(id.ne_all(excluded)),
+                )
+                .order_by(scheduled_at.asc())
+                .select(Post::as_select())
+                .first(conn)
+                .optional()
+                .expect("error fetching next scheduled post"))
+        })
+    }
+
+    /// Records a failed send, postponing the next attempt with exponential
+    /// backoff and marking the post as failed after `max_attempts`.
+    pub async fn record_send_failure<T>(
+        &self,
+        ids: T,
+        error: String,
+        max_attempts: i32,
+        backoff: Duration,
+    ) -> anyhow::Result<()>
+    where
+        T: IntoIterator<Item = Uuid>,
+    {
+        use crate::database::schema::posts::dsl::{
+            attempts, failed, id, last_error, next_attempt_at, posts,
+        };
+
+        self.conn.lock().await.transaction(|conn| {
+            for post_id in ids {
+                let post_attempts: i32 = posts
+                    .find(UUID(post_id))
+                    .select(attempts)
+                    .first::<i32>(conn)
+                    .expect("error fetching post attempts")
+                    + 1;
+                let delay = backoff.saturating_mul(2u32.saturating_pow(post_attempts as u32 - 1));
+                diesel::update(posts.filter(id.eq(UUID(post_id))))
+                    .set((
+                        attempts.eq(post_attempts),
+                        last_error.eq(&error),
+                        next_attempt_at.eq(Utc::now().naive_utc()
+                            + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX)),
+                        failed.eq(post_attempts >= max_attempts),
+                    ))
+                    .execute(conn)
+                    .expect("error recording send failure");
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn fetch_failed_posts(&self) -> anyhow::Result<Vec<Post>> {
+        use crate::database::schema::posts::dsl::{created_datetime, deleted, failed, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(posts
+                .filter(failed.eq(true).and(deleted.eq(false)))
+                .order_by(created_datetime.asc())
+                .select(Post::as_select())
+                .load(conn)
+                .expect("error fetching failed posts"))
+        })
+    }
+
+    /// Clears the failure state of the given failed posts, or of every failed
+    /// post when `ids` is `None`. Returns the number of requeued posts.
+    pub async fn requeue_failed_posts(&self, ids: Option<Vec<Uuid>>) -> anyhow::Result<usize> {
+        use crate::database::schema::posts::dsl::{
+            attempts, failed, id, last_error, next_attempt_at, posts,
+        };
+
+        let count = self.conn.lock().await.transaction(|conn| {
+            let mut query = diesel::update(posts.filter(failed.eq(true))).into_boxed();
+            if let Some(ids) = ids {
+                query = query.filter(id.eq_any(ids.into_iter().map(UUID::from)));
+            }
+            Ok::<_, anyhow::Error>(
+                query
+                    .set((
+                        attempts.eq(0),
+                        last_error.eq(None::<String>),
+                        next_attempt_at.eq(None::<NaiveDateTime>),
+                        failed.eq(false),
+                    ))
+                    .execute(conn)
+                    .expect("error requeueing failed posts"),
+            )
+        })?;
+        self.schedule_changed.notify_one();
+        Ok(count)
+    }
+
+    pub async fn set_post_priority(&self, post_id: Uuid, value: i32) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{id, posts, priority};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(priority.eq(value))
+                .execute(conn)
+                .expect("error updating post priority");
+            Ok(())
+        })
+    }
+
+    pub async fn set_post_caption_template(
+        &self,
+        post_id: Uuid,
+        template: Option<String>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{caption_template, id, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(caption_template.eq(template))
+                .execute(conn)
+                .expect("error updating post caption template");
+            Ok(())
+        })
+    }
+
+    /// Sets the spoiler blur of a post, including every item of an album.
+    pub async fn set_post_spoiler(&self, post_id: Uuid, value: bool) -> anyhow::Result<()> {
+        use crate::database::schema::{post_items, posts};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts::table.filter(posts::id.eq(UUID(post_id))))
+                .set(posts::spoiler.eq(value))
+                .execute(conn)
+                .expect("error updating post spoiler");
+            diesel::update(post_items::table.filter(post_items::post_id.eq(UUID(post_id))))
+                .set(post_items::spoiler.eq(value))
+                .execute(conn)
+                .expect("error updating post item spoilers");
+            Ok(())
+        })
+    }
+
+    pub async fn set_post_silent(&self, post_id: Uuid, value: Option<bool>) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{id, posts, silent};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(silent.eq(value))
+                .execute(conn)
+                .expect("error updating post silence");
+            Ok(())
+        })
+    }
+
+    pub async fn set_post_protect_content(
+        &self,
+        post_id: Uuid,
+        value: Option<bool>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{id, posts, protect_content};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(protect_content.eq(value))
+                .execute(conn)
+                .expect("error updating post content protection");
+            Ok(())
+        })
+    }
+
+    pub async fn queue_order(&self, default: QueueOrder) -> anyhow::Result<QueueOrder> {
+        Ok(self
+            .get_setting(QUEUE_ORDER_SETTING)
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default))
+    }
+
+    pub async fn fetch_targets(&self) -> anyhow::Result<Vec<Target>> {
+        use crate::database::schema::targets::dsl::{id, targets};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(targets
+                .order_by(id)
+                .select(Target::as_select())
+                .load(conn)
+                .expect("error fetching targets"))
+        })
+    }
+
+    pub async fn fetch_target(&self, target_id: i32) -> anyhow::Result<Option<Target>> {
+        use crate::database::schema::targets::dsl::targets;
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(targets
+                .find(target_id)
+                .select(Target::as_select())
+                .first(conn)
+                .optional()
+                .expect("error fetching target"))
+        })
+    }
+
+    pub async fn fetch_target_by_name(&self, target_name: &str) -> anyhow::Result<Option<Target>> {
+        use crate::database::schema::targets::dsl::{name, targets};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(targets
+                .filter(name.eq(target_name))
+                .select(Target::as_select())
+                .first(conn)
+                .optional()
+                .expect("error fetching target by name"))
+        })
+    }
+
+    pub async fn save_target(
+        &self,
+        target_name: &str,
+        target_chat_id: i64,
+        target_interval: Option<Duration>,
+        target_schedule: Option<String>,
+        target_group_threshold: i64,
+    ) -> anyhow::Result<Target> {
+        use crate::database::schema::targets::dsl::{
+            chat_id, group_threshold, interval_secs, name, schedule, targets,
+        };
+
+        let values = (
+            name.eq(target_name),
+            chat_id.eq(target_chat_id),
+            interval_secs.eq(target_interval.map(|v| v.as_secs() as i64)),
+            schedule.eq(target_schedule),
+            group_threshold.eq(target_group_threshold),
+        );
+
+        let target = self.conn.lock().await.transaction(|conn| {
+            Ok::<_, anyhow::Error>(
+                diesel::insert_into(targets)
+                    .values(values.clone())
+                    .on_conflict(name)
+                    .do_update()
+                    .set(values)
+                    .returning(Target::as_returning())
+                    .get_result(conn)
+                    .expect("error saving target"),
+            )
+        })?;
+        self.targets_changed.notify_one();
+        Ok(target)
+    }
+
+    pub async fn set_target_caption_template(
+        &self,
+        target: i32,
+        template: Option<String>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::targets::dsl::{caption_template, targets};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(targets.find(target))
+                .set(caption_template.eq(template))
+                .execute(conn)
+                .expect("error updating target caption template");
+            Ok(())
+        })
+    }
+
+    pub async fn set_target_silent_hours(
+        &self,
+        target: i32,
+        hours: Option<String>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::targets::dsl::{silent_hours, targets};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(targets.find(target))
+                .set(silent_hours.eq(hours))
+                .execute(conn)
+                .expect("error updating target silent hours");
+            Ok(())
+        })
+    }
+
+    pub async fn set_target_protect_content(
+        &self,
+        target: i32,
+        value: Option<bool>,
+    ) -> anyhow::Result<()> {
+        use crate::database::schema::targets::dsl::{protect_content, targets};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(targets.find(target))
+                .set(protect_content.eq(value))
+                .execute(conn)
+                .expect("error updating target content protection");
+            Ok(())
+        })
+    }
+
+    pub async fn fetch_sender_target(&self, sender_chat_id: i64) -> anyhow::Result<Option<i32>> {
+        use crate::database::schema::sender_targets::dsl::{sender_targets, target_id};
+
+        self.conn.lock().await.transaction(|conn| {
+            Ok(sender_targets
+                .find(sender_chat_id)
+                .select(target_id)
+                .first(conn)
+                .optional()
+                .expect("error fetching sender target"))
+        })
+    }
+
+    pub async fn set_sender_target(&self, sender_chat_id: i64, target: i32) -> anyhow::Result<()> {
+        use crate::database::schema::sender_targets::dsl::{chat_id, sender_targets, target_id};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::insert_into(sender_targets)
+                .values((chat_id.eq(sender_chat_id), target_id.eq(target)))
+                .on_conflict(chat_id)
+                .do_update()
+                .set(target_id.eq(target))
+                .execute(conn)
+                .expect("error saving sender target");
+            Ok(())
+        })
+    }
+
+    pub async fn set_post_target(&self, post_id: Uuid, target: i32) -> anyhow::Result<()> {
+        use crate::database::schema::posts::dsl::{id, posts, target_id};
+
+        self.conn.lock().await.transaction(|conn| {
+            diesel::update(posts.filter(id.eq(UUID(post_id))))
+                .set(target_id.eq(target))
+                .execute(conn)
+                .expect("error updating post target");
+            Ok(())
+        })
+    }
+}
+
+/// Unsent posts of a target that are due for the regular queue: not deleted,
+/// not pinned to an exact time, not waiting out a send failure and not in
+/// `excluded`.
+fn queued_posts<'a>(target: i32, excluded: &[UUID]) -> schema::posts::BoxedQuery<'a, Sqlite> {
+    use crate::database::schema::posts::dsl::{
+        deleted, failed, id, is_sent, next_attempt_at, posts, scheduled_at, target_id,
+    };
+
+    posts
+        .filter(
+            is_sent
+                .eq(false)
+                .and(deleted.eq(false))
+                .and(target_id.eq(target))
+                .and(scheduled_at.is_null())
+                .and(failed.eq(false))
+                .and(
+                    next_attempt_at
+                        .is_null()
+                        .or(next_attempt_at.le(Utc::now().naive_utc())),
+                )
+                .and(id.ne_all(excluded.to_vec())),
+        )
+        .into_boxed()
+}
+
+fn order_unsent<'a>(
+    conn: &mut SqliteConnection,
+    simulated: &std::sync::Mutex<Option<HashMap<String, String>>>,
+    query: schema::posts::BoxedQuery<'a, Sqlite>,
+    target: i32,
+    order: QueueOrder,
+    excluded: &[UUID],
+) -> schema::posts::BoxedQuery<'a, Sqlite> {
+    use crate::database::schema::posts::dsl::{created_datetime, priority};
+
+    let query = query.order_by(priority.desc());
+    match order {
+        QueueOrder::Random => query.then_order_by(random()),
+        QueueOrder::Fifo => query.then_order_by(created_datetime.asc()),
+        QueueOrder::Lifo => query.then_order_by(created_datetime.desc()),
+        QueueOrder::ShuffleBag => {
+            let cutoff = shuffle_bag_cutoff(conn, simulated, target, excluded);
+            query
+                .then_order_by(created_datetime.gt(cutoff))
+                .then_order_by(random())
+        }
+    }
+}
+
+/// Returns the creation time up to which posts belong to the current bag,
+/// refilling the bag with everything queued so far once it is drained. A
+/// simulated sender keeps its bag in `simulated` rather than the database.
+fn shuffle_bag_cutoff(
+    conn: &mut SqliteConnection,
+    simulated: &std::sync::Mutex<Option<HashMap<String, String>>>,
+    target: i32,
+    excluded: &[UUID],
+) -> NaiveDateTime {
+    use crate::database::schema::{
+        posts::dsl::created_datetime,
+        settings::dsl::{key, settings, value},
+    };
+
+    let setting_key = format!("shuffle_bag_cutoff:{target}");
+    let simulated_cutoff = simulated
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|simulated| simulated.get(&setting_key).cloned());
+    let cutoff = match simulated_cutoff {
+        Some(cutoff) => Some(cutoff),
+        None => settings
+            .find(&setting_key)
+            .select(value)
+            .first::<String>(conn)
+            .optional()
+            .expect("error fetching shuffle bag cutoff"),
+    }
+    .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+    .map(|v| v.naive_utc());
+
+    if let Some(cutoff) = cutoff {
+        let remaining: i64 = queued_posts(target, excluded)
+            .filter(created_datetime.le(cutoff))
+            .count()
+            .get_result(conn)
+            .expect("error counting shuffle bag posts");
+        if remaining > 0 {
+            return cutoff;
+        }
+    }
+
+    let cutoff = Utc::now().naive_utc();
+    let cutoff_value = cutoff.and_utc().to_rfc3339();
+    if let Some(simulated) = simulated.lock().unwrap().as_mut() {
+        simulated.insert(setting_key, cutoff_value);
+        return cutoff;
+    }
+    diesel::insert_into(settings)
+        .values((key.eq(&setting_key), value.eq(&cutoff_value)))
+        .on_conflict(key)
+        .do_update()
+        .set(value.eq(&cutoff_value))
+        .execute(conn)
+        .expect("error saving shuffle bag cutoff");
+    cutoff
 }
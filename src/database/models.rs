@@ -1,4 +1,5 @@
-use chrono::NaiveDateTime;
+use super::DEFAULT_TARGET_ID;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use diesel::{
     AsExpression, FromSqlRow,
     backend::Backend,
@@ -10,10 +11,13 @@ use diesel::{
 };
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::time::Duration;
+use teloxide::types::MessageEntity;
 use uuid::Uuid;
 
 #[derive(Debug, Default, Clone, Copy, FromSqlRow, AsExpression, Hash, Eq, PartialEq)]
 #[diesel(sql_type = Text)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct UUID(pub uuid::Uuid);
 
 impl<B: Backend> FromSql<Text, B> for UUID
@@ -22,7 +26,7 @@ where
 {
     fn from_sql(bytes: <B as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
         let value = String::from_sql(bytes)?;
-        uuid::Uuid::from_str(&value.as_str())
+        uuid::Uuid::from_str(value.as_str())
             .map(UUID)
             .map_err(|e| e.into())
     }
@@ -46,7 +50,7 @@ impl From<UUID> for Uuid {
 
 impl From<Uuid> for UUID {
     fn from(value: Uuid) -> Self {
-        Self { 0: value }
+        Self(value)
     }
 }
 
@@ -56,6 +60,21 @@ pub enum MediaType {
     Photo,
     Video,
     Animation,
+    /// Several photos and videos submitted together, see [`PostItem`].
+    Album,
+    /// An image published as an uncompressed file.
+    Document,
+    /// A text message, its body kept as the post's caption.
+    Text,
+    Audio,
+    Voice,
+    Sticker,
+    VideoNote,
+}
+
+impl MediaType {
+    /// Media types Telegram accepts together in a `sendMediaGroup` album.
+    pub const GROUPABLE: [MediaType; 2] = [MediaType::Photo, MediaType::Video];
 }
 
 impl<B: Backend> FromSql<Text, B> for MediaType
@@ -68,6 +87,13 @@ where
             "photo" => Ok(MediaType::Photo),
             "video" => Ok(MediaType::Video),
             "anim" => Ok(MediaType::Animation),
+            "album" => Ok(MediaType::Album),
+            "doc" => Ok(MediaType::Document),
+            "text" => Ok(MediaType::Text),
+            "audio" => Ok(MediaType::Audio),
+            "voice" => Ok(MediaType::Voice),
+            "sticker" => Ok(MediaType::Sticker),
+            "vnote" => Ok(MediaType::VideoNote),
             _ => Err("invalid MediaType variant".into()),
         }
     }
@@ -82,6 +108,13 @@ where
             MediaType::Photo => "photo",
             MediaType::Video => "video",
             MediaType::Animation => "anim",
+            MediaType::Album => "album",
+            MediaType::Document => "doc",
+            MediaType::Text => "text",
+            MediaType::Audio => "audio",
+            MediaType::Voice => "voice",
+            MediaType::Sticker => "sticker",
+            MediaType::VideoNote => "vnote",
         });
         Ok(IsNull::No)
     }
@@ -93,6 +126,86 @@ impl Display for MediaType {
             MediaType::Photo => "photo",
             MediaType::Video => "video",
             MediaType::Animation => "anim",
+            MediaType::Album => "album",
+            MediaType::Document => "doc",
+            MediaType::Text => "text",
+            MediaType::Audio => "audio",
+            MediaType::Voice => "voice",
+            MediaType::Sticker => "sticker",
+            MediaType::VideoNote => "vnote",
+        })
+    }
+}
+
+/// Paused state of publishing, stored as the `paused_until` setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pause {
+    Indefinitely,
+    Until(DateTime<Utc>),
+}
+
+impl FromStr for Pause {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indefinitely" => Ok(Pause::Indefinitely),
+            value => Ok(Pause::Until(
+                DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc),
+            )),
+        }
+    }
+}
+
+impl Display for Pause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pause::Indefinitely => f.write_str("indefinitely"),
+            Pause::Until(until) => f.write_str(&until.to_rfc3339()),
+        }
+    }
+}
+
+pub const PRIORITY_LOW: i32 = -1;
+pub const PRIORITY_NORMAL: i32 = 0;
+pub const PRIORITY_HIGH: i32 = 1;
+/// Set by `/next`, sorts before every other priority.
+pub const PRIORITY_NEXT: i32 = 2;
+
+/// Order in which the sender picks queued posts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueOrder {
+    Random,
+    Fifo,
+    Lifo,
+    /// Posts queued before the current bag was filled go out first, in
+    /// random order; the bag is refilled once it is drained.
+    ShuffleBag,
+}
+
+impl FromStr for QueueOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "random" => Ok(QueueOrder::Random),
+            "fifo" => Ok(QueueOrder::Fifo),
+            "lifo" => Ok(QueueOrder::Lifo),
+            "shuffle-bag" | "shufflebag" | "bag" => Ok(QueueOrder::ShuffleBag),
+            _ => anyhow::bail!(
+                "unknown queue order {s:?}, expected random, fifo, lifo or shuffle-bag"
+            ),
+        }
+    }
+}
+
+impl Display for QueueOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            QueueOrder::Random => "random",
+            QueueOrder::Fifo => "fifo",
+            QueueOrder::Lifo => "lifo",
+            QueueOrder::ShuffleBag => "shuffle-bag",
         })
     }
 }
@@ -109,11 +222,134 @@ pub struct Post {
     pub created_datetime: NaiveDateTime,
     pub sent_datetime: Option<NaiveDateTime>,
     pub image_hash: Option<String>,
+    pub target_id: i32,
+    pub priority: i32,
+    pub scheduled_at: Option<NaiveDateTime>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<NaiveDateTime>,
+    pub failed: bool,
+    pub caption_template: Option<String>,
+    pub contributor: Option<String>,
+    pub source_url: Option<String>,
+    pub tags: Option<String>,
+    pub caption: Option<String>,
+    /// JSON-encoded formatting entities of `caption`.
+    pub caption_entities: Option<String>,
+    /// Set when a sent post was removed from its channel again.
+    pub unpublished: bool,
+    /// Publish the media behind Telegram's spoiler blur.
+    pub spoiler: bool,
+    /// Overrides whether the post goes out without a notification.
+    pub silent: Option<bool>,
+    /// Overrides whether the post can be forwarded and saved.
+    pub protect_content: Option<bool>,
+    /// Telegram's identifier of the file, which unlike `file_id` stays the
+    /// same when the file is forwarded or sent again.
+    pub file_unique_id: Option<String>,
+    /// Identifies a video or animation by its thumbnail, duration and
+    /// dimensions, to catch the same clip uploaded again as a new file.
+    pub clip_fingerprint: Option<String>,
 }
 
 impl Post {
-    pub fn is_photo(&self) -> bool {
-        self.media_type == MediaType::Photo
+    pub fn is_groupable(&self) -> bool {
+        MediaType::GROUPABLE.contains(&self.media_type)
+    }
+
+    pub fn is_published(&self) -> bool {
+        self.is_sent && !self.unpublished
+    }
+
+    pub fn entities(&self) -> Vec<MessageEntity> {
+        self.caption_entities
+            .as_deref()
+            .and_then(|v| serde_json::from_str(v).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Attributes of a post about to be queued; see [`crate::database::Database::create_post`].
+pub struct NewPost {
+    pub id: Option<Uuid>,
+    pub media_type: MediaType,
+    pub file_id: String,
+    pub image_hash: Option<String>,
+    pub file_unique_id: Option<String>,
+    pub clip_fingerprint: Option<String>,
+    pub target_id: i32,
+    pub contributor: Option<String>,
+    pub source_url: Option<String>,
+    pub tags: Option<String>,
+    pub caption: Option<String>,
+    pub caption_entities: Option<String>,
+    pub spoiler: bool,
+    /// Media of an album post, in order.
+    pub items: Vec<NewPostItem>,
+}
+
+impl NewPost {
+    pub fn new(media_type: MediaType, file_id: String) -> Self {
+        Self {
+            id: None,
+            media_type,
+            file_id,
+            image_hash: None,
+            file_unique_id: None,
+            clip_fingerprint: None,
+            target_id: DEFAULT_TARGET_ID,
+            contributor: None,
+            source_url: None,
+            tags: None,
+            caption: None,
+            caption_entities: None,
+            spoiler: false,
+            items: vec![],
+        }
+    }
+}
+
+/// A photo or video of an album about to be queued.
+pub struct NewPostItem {
+    pub media_type: MediaType,
+    pub file_id: String,
+    pub image_hash: Option<String>,
+    pub spoiler: bool,
+    pub file_unique_id: Option<String>,
+    pub clip_fingerprint: Option<String>,
+}
+
+/// A single photo or video of an album post.
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = crate::database::schema::post_items)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PostItem {
+    #[diesel(serialize_as = UUID, deserialize_as = UUID)]
+    pub post_id: Uuid,
+    pub position: i32,
+    pub media_type: MediaType,
+    pub file_id: String,
+    pub image_hash: Option<String>,
+    /// Publish the item behind Telegram's spoiler blur. The post's own
+    /// `spoiler` tells whether any of its items is one.
+    pub spoiler: bool,
+    pub file_unique_id: Option<String>,
+    pub clip_fingerprint: Option<String>,
+}
+
+impl From<&Post> for PostItem {
+    /// The only item of a post that is not an album.
+    fn from(post: &Post) -> Self {
+        Self {
+            post_id: post.id,
+            position: 0,
+            media_type: post.media_type.clone(),
+            file_id: post.file_id.clone(),
+            image_hash: post.image_hash.clone(),
+            spoiler: post.spoiler,
+            file_unique_id: post.file_unique_id.clone(),
+            clip_fingerprint: post.clip_fingerprint.clone(),
+        }
     }
 }
 
@@ -140,3 +376,40 @@ pub struct PostMessageId {
     #[diesel(serialize_as = UUID, deserialize_as = UUID)]
     pub post_id: Uuid,
 }
+
+#[derive(Queryable, Selectable, Clone, Debug)]
+#[diesel(table_name = crate::database::schema::targets)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Target {
+    pub id: i32,
+    pub name: String,
+    pub chat_id: i64,
+    pub interval_secs: Option<i64>,
+    pub schedule: Option<String>,
+    pub group_threshold: i64,
+    pub caption_template: Option<String>,
+    /// Overrides the configured silent hours, see [`SilentHours`].
+    ///
+    /// [`SilentHours`]: crate::scheduling::SilentHours
+    pub silent_hours: Option<String>,
+    pub protect_content: Option<bool>,
+}
+
+impl Target {
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval_secs.map(|v| Duration::from_secs(v as u64))
+    }
+}
+
+/// A named lease held by one instance at a time, see [`Database::acquire_lease`].
+///
+/// [`Database::acquire_lease`]: crate::database::Database::acquire_lease
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(table_name = crate::database::schema::leases)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Lease {
+    pub name: String,
+    pub holder: String,
+    pub heartbeat_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
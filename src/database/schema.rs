@@ -1,5 +1,27 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    leases (name) {
+        name -> Text,
+        holder -> Text,
+        heartbeat_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    post_items (post_id, position) {
+        post_id -> Text,
+        position -> Integer,
+        media_type -> Text,
+        file_id -> Text,
+        image_hash -> Nullable<Text>,
+        spoiler -> Bool,
+        file_unique_id -> Nullable<Text>,
+        clip_fingerprint -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     post_message_ids (rowid) {
         rowid -> Integer,
@@ -19,6 +41,53 @@ diesel::table! {
         sent_datetime -> Nullable<Timestamp>,
         image_hash -> Nullable<Text>,
         deleted -> Bool,
+        target_id -> Integer,
+        priority -> Integer,
+        scheduled_at -> Nullable<Timestamp>,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Nullable<Timestamp>,
+        failed -> Bool,
+        caption_template -> Nullable<Text>,
+        contributor -> Nullable<Text>,
+        source_url -> Nullable<Text>,
+        tags -> Nullable<Text>,
+        caption -> Nullable<Text>,
+        caption_entities -> Nullable<Text>,
+        unpublished -> Bool,
+        spoiler -> Bool,
+        silent -> Nullable<Bool>,
+        protect_content -> Nullable<Bool>,
+        file_unique_id -> Nullable<Text>,
+        clip_fingerprint -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    sender_targets (chat_id) {
+        chat_id -> BigInt,
+        target_id -> Integer,
+    }
+}
+
+diesel::table! {
+    settings (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    targets (id) {
+        id -> Integer,
+        name -> Text,
+        chat_id -> BigInt,
+        interval_secs -> Nullable<BigInt>,
+        schedule -> Nullable<Text>,
+        group_threshold -> BigInt,
+        caption_template -> Nullable<Text>,
+        silent_hours -> Nullable<Text>,
+        protect_content -> Nullable<Bool>,
     }
 }
 
@@ -34,6 +103,18 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(post_items -> posts (post_id));
 diesel::joinable!(post_message_ids -> posts (post_id));
+diesel::joinable!(posts -> targets (target_id));
+diesel::joinable!(sender_targets -> targets (target_id));
 
-diesel::allow_tables_to_appear_in_same_query!(post_message_ids, posts, upload_tasks,);
+diesel::allow_tables_to_appear_in_same_query!(
+    leases,
+    post_items,
+    post_message_ids,
+    posts,
+    sender_targets,
+    settings,
+    targets,
+    upload_tasks,
+);
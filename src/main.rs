@@ -1,6 +1,8 @@
+mod captions;
 mod cli;
 mod config;
 mod database;
+mod scheduling;
 mod telegram_handlers;
 mod utils;
 mod workers;
@@ -10,8 +12,13 @@ use crate::{
     workers::{run_bot, run_sender, run_server, run_uploader},
 };
 use dotenvy::dotenv;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use teloxide::prelude::*;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -24,16 +31,79 @@ async fn main() -> anyhow::Result<()> {
     let db_path = format!("dbs/{}.sqlite3", &cfg.db_name);
     let db = Arc::new(Database::open(&db_path)?);
 
+    db.save_target(
+        "default",
+        cfg.target_chat_id,
+        cfg.interval,
+        cfg.schedule.as_ref().map(|v| v.to_string()),
+        cfg.group_threshold,
+    )
+    .await?;
+
     let bot = Bot::new(&cfg.bot_token);
 
+    let shutdown = CancellationToken::new();
+    tokio::spawn(wait_for_signal(shutdown.clone()));
+
+    let mut workers = JoinSet::new();
+    let mut names = HashMap::new();
     if cfg.with_api {
-        tokio::spawn(run_server(cfg.clone(), db.clone()));
-        tokio::spawn(run_uploader(bot.clone(), db.clone(), cfg.clone()));
+        let server = run_server(cfg.clone(), db.clone(), shutdown.clone());
+        names.insert(workers.spawn(server).id(), "API server");
+        let uploader = run_uploader(bot.clone(), db.clone(), cfg.clone(), shutdown.clone());
+        names.insert(workers.spawn(uploader).id(), "uploader");
     }
+    let sender = run_sender(bot.clone(), db.clone(), cfg.clone(), shutdown.clone());
+    names.insert(workers.spawn(sender).id(), "sender");
+    let bot = run_bot(bot.clone(), db.clone(), cfg.clone(), shutdown.clone());
+    names.insert(workers.spawn(bot).id(), "bot");
 
-    tokio::spawn(run_sender(bot.clone(), db.clone(), cfg.clone()));
-
-    run_bot(bot.clone(), db.clone(), cfg.clone()).await;
+    // Any worker stopping takes the others down with it.
+    let (mut stopped, mut failed) = (0, 0);
+    while let Some(result) = workers.join_next_with_id().await {
+        match result {
+            Ok((id, Ok(()))) => {
+                log::info!("{} stopped", names[&id]);
+                stopped += 1;
+            }
+            Ok((id, Err(e))) => {
+                log::error!("{} failed: {e:?}", names[&id]);
+                failed += 1;
+            }
+            Err(e) => {
+                log::error!("{} panicked: {e:?}", names[&e.id()]);
+                failed += 1;
+            }
+        }
+        shutdown.cancel();
+    }
 
+    log::info!("Shutdown complete: {stopped} workers stopped, {failed} failed");
+    if failed > 0 {
+        anyhow::bail!("{failed} worker(s) failed");
+    }
     Ok(())
 }
+
+/// Requests shutdown on Ctrl-C or SIGTERM.
+async fn wait_for_signal(shutdown: CancellationToken) {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                log::error!("failed to listen for SIGTERM: {e:?}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+
+    log::info!("Shutting down, waiting for workers to finish...");
+    shutdown.cancel();
+}
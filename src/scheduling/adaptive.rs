@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+/// Paces the sender so the current queue lasts for `runway`.
+#[derive(Debug, Clone)]
+pub struct AdaptivePacing {
+    pub runway: Duration,
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+}
+
+impl AdaptivePacing {
+    /// Returns the interval until the next send and how many posts to send
+    /// at once. Once the interval would drop below `min_interval`, posts are
+    /// grouped instead, growing the group with the queue up to `max_group`.
+    /// An empty queue is checked again after `min_interval`, so a newly
+    /// queued post does not wait out `max_interval`.
+    pub fn plan(&self, queued: i64, max_group: i64) -> (Duration, i64) {
+        if queued <= 0 {
+            return (self.min_interval, 1);
+        }
+
+        let ideal = self.runway.div_f64(queued as f64);
+        if ideal >= self.min_interval {
+            return (ideal.min(self.max_interval), 1);
+        }
+
+        let group = (self.min_interval.as_secs_f64() / ideal.as_secs_f64()).ceil() as i64;
+        (self.min_interval, group.clamp(1, max_group.max(1)))
+    }
+}
@@ -0,0 +1,90 @@
+use super::{AdaptivePacing, PostingWindows, PublicationSlots};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::time::Duration;
+
+/// Upper bound on simulated sends, so that huge queues or slot-less
+/// schedules cannot stall a forecast.
+const MAX_FORECAST_SENDS: usize = 100_000;
+
+/// How a target publishes, mirroring the decisions of the sender.
+pub struct Cadence<'a> {
+    pub interval: Option<Duration>,
+    pub slots: Option<&'a PublicationSlots>,
+    pub adaptive: Option<&'a AdaptivePacing>,
+    pub windows: Option<&'a PostingWindows>,
+    pub group_threshold: i64,
+    pub group_size: i64,
+}
+
+impl Cadence<'_> {
+    /// Interval after a send and number of posts in it with `queued` posts
+    /// waiting.
+    fn step(&self, queued: i64) -> (Duration, i64) {
+        let interval = self.interval.unwrap_or_default();
+        match self.adaptive.filter(|_| self.slots.is_none()) {
+            Some(pacing) => pacing.plan(queued, self.group_size),
+            None if self.group_threshold > 0 && queued > self.group_threshold => {
+                (interval, self.group_size)
+            }
+            None => (interval, 1),
+        }
+    }
+
+    /// Estimates the next send given the time of the previous one.
+    pub fn next_send(
+        &self,
+        queued: i64,
+        last_sent: Option<DateTime<Tz>>,
+        now: DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        let at = match (self.slots, last_sent) {
+            (Some(slots), _) => slots.next_after(now)?,
+            (None, Some(last_sent)) if self.interval.is_some() || self.adaptive.is_some() => {
+                (last_sent + self.step(queued).0).max(now)
+            }
+            (None, None) if self.interval.is_some() || self.adaptive.is_some() => now,
+            (None, _) => return None,
+        };
+        match self.windows {
+            Some(windows) if windows.current_window_end(at).is_none() => {
+                windows.next_window_start(at)
+            }
+            _ => Some(at),
+        }
+    }
+
+    /// Estimates when the last of `queued` posts goes out, with the first
+    /// send happening at `first_send`. Grouping assumes every queued post
+    /// can be grouped.
+    pub fn run_out(&self, queued: i64, first_send: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let mut remaining = queued;
+        let mut at = first_send;
+        for _ in 0..MAX_FORECAST_SENDS {
+            if remaining <= 0 {
+                return Some(at);
+            }
+
+            if let Some(windows) = self.windows
+                && windows.current_window_end(at).is_none()
+            {
+                at = match self.slots {
+                    Some(slots) => slots.next_after(at)?,
+                    None => windows.next_window_start(at)?,
+                };
+                continue;
+            }
+
+            let (interval, group_size) = self.step(remaining);
+            remaining -= group_size;
+            if remaining <= 0 {
+                return Some(at);
+            }
+            at = match self.slots {
+                Some(slots) => slots.next_after(at)?,
+                None => at + interval,
+            };
+        }
+        None
+    }
+}
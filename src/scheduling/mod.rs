@@ -0,0 +1,45 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use tokio::time::Instant;
+
+mod adaptive;
+mod forecast;
+mod slots;
+mod windows;
+
+pub use adaptive::AdaptivePacing;
+pub use forecast::Cadence;
+pub use slots::PublicationSlots;
+pub use windows::{PostingWindows, SilentHours};
+
+/// Resolves a local wall-clock time, picking the earlier instant for
+/// ambiguous times and moving times skipped by a DST jump forward past the
+/// gap.
+pub fn localize<T: TimeZone>(tz: &T, datetime: NaiveDateTime) -> DateTime<T> {
+    tz.from_local_datetime(&datetime)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(datetime + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| tz.from_utc_datetime(&datetime))
+}
+
+pub fn instant_at<T: TimeZone>(datetime: &DateTime<T>) -> Instant {
+    Instant::now()
+        + (datetime.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default()
+}
+
+/// Parses an RFC 3339 timestamp or a wall-clock `YYYY-MM-DD HH:MM` in `tz`.
+pub fn parse_local_datetime(value: &str, tz: &Tz) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|datetime| localize(tz, datetime).with_timezone(&Utc))
+}
@@ -0,0 +1,212 @@
+use super::localize;
+use chrono::{DateTime, Days, NaiveTime};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// Publication slots built from cron expressions and explicit times of day.
+///
+/// Format: `;`-separated entries, each either a cron expression
+/// (`0 */2 * * *`, seconds field optional) or a `,`-separated list of `HH:MM`
+/// times, e.g. `0 */2 * * *;12:30,18:45`. Days of the week are numbered as in
+/// standard cron: 0 or 7 is Sunday, 1 is Monday.
+#[derive(Debug, Clone, Default)]
+pub struct PublicationSlots {
+    source: String,
+    crons: Vec<Schedule>,
+    times: Vec<NaiveTime>,
+}
+
+impl PublicationSlots {
+    /// Returns the first slot strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let from_crons = self
+            .crons
+            .iter()
+            .filter_map(|schedule| schedule.after(&after).next());
+        let from_times = (0..2).flat_map(|offset| {
+            let date = after.date_naive() + Days::new(offset);
+            self.times
+                .iter()
+                .map(move |time| localize(&tz, date.and_time(*time)))
+        });
+        from_crons
+            .chain(from_times)
+            .filter(|slot| *slot > after)
+            .min()
+    }
+}
+
+/// Turns a standard cron expression into the dialect of the `cron` crate,
+/// which requires a seconds field and numbers days of the week from 1 for
+/// Sunday.
+fn cron_expression(entry: &str) -> anyhow::Result<String> {
+    let mut fields: Vec<String> = entry.split_whitespace().map(str::to_string).collect();
+    if fields.len() == 5 {
+        fields.insert(0, "0".to_string());
+    }
+    if let Some(days) = fields.get_mut(5) {
+        *days = days_of_week(days)?;
+    }
+    Ok(fields.join(" "))
+}
+
+/// Renumbers the numeric parts of a standard day-of-week field, expanding
+/// ranges and steps into lists. Named days are left as they are.
+fn days_of_week(field: &str) -> anyhow::Result<String> {
+    let mut parts = vec![];
+    for part in field.split(',') {
+        let (base, step) = match part.split_once('/') {
+            Some((base, step)) => (base, Some(step.parse::<usize>()?)),
+            None => (part, None),
+        };
+        let (from, to) = match base.split_once('-') {
+            _ if base == "*" || base == "?" => match step {
+                Some(_) => (0, 6),
+                None => {
+                    parts.push(part.to_string());
+                    continue;
+                }
+            },
+            Some((from, to)) => match (from.parse::<u32>(), to.parse::<u32>()) {
+                (Ok(from), Ok(to)) => (from, to),
+                _ => {
+                    parts.push(part.to_string());
+                    continue;
+                }
+            },
+            None => match base.parse::<u32>() {
+                Ok(day) if step.is_some() => (day, 6),
+                Ok(day) => (day, day),
+                Err(_) => {
+                    parts.push(part.to_string());
+                    continue;
+                }
+            },
+        };
+        if from > to || to > 7 {
+            anyhow::bail!("invalid day of week {part:?}");
+        }
+        if step == Some(0) {
+            anyhow::bail!("invalid step in {part:?}");
+        }
+        parts.extend(
+            (from..=to)
+                .step_by(step.unwrap_or(1))
+                .map(|day| (day % 7 + 1).to_string()),
+        );
+    }
+    Ok(parts.join(","))
+}
+
+impl FromStr for PublicationSlots {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = Self {
+            source: s.trim().to_string(),
+            ..Self::default()
+        };
+        for entry in s.split(';').map(str::trim).filter(|v| !v.is_empty()) {
+            // Cron expressions have five or six fields, time lists only one
+            // per comma-separated item.
+            if entry.split_whitespace().count() >= 5 {
+                let expression = cron_expression(entry)
+                    .and_then(|expression| Ok(Schedule::from_str(&expression)?))
+                    .map_err(|e| anyhow::anyhow!("invalid cron expression {entry:?}: {e}"))?;
+                result.crons.push(expression);
+            } else {
+                for time in entry.split(',').filter(|v| !v.trim().is_empty()) {
+                    result
+                        .times
+                        .push(NaiveTime::parse_from_str(time.trim(), "%H:%M")?);
+                }
+            }
+        }
+        if result.crons.is_empty() && result.times.is_empty() {
+            anyhow::bail!("schedule must contain at least one slot");
+        }
+        Ok(result)
+    }
+}
+
+impl Display for PublicationSlots {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Weekday, Weekday::*};
+    use chrono_tz::Europe::Berlin;
+
+    /// Weekdays of the slots in the week starting on Sunday 2026-10-18.
+    fn weekdays(schedule: &str) -> Vec<Weekday> {
+        let slots: PublicationSlots = schedule.parse().unwrap();
+        let mut at = Berlin.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+        let end = Berlin.with_ymd_and_hms(2026, 10, 25, 0, 0, 0).unwrap();
+        let mut result = vec![];
+        while let Some(slot) = slots.next_after(at).filter(|slot| *slot < end) {
+            result.push(slot.weekday());
+            at = slot;
+        }
+        result
+    }
+
+    #[test]
+    fn standard_weekday_numbers() {
+        assert_eq!(weekdays("0 9 * * 1-5"), [Mon, Tue, Wed, Thu, Fri]);
+        assert_eq!(weekdays("0 9 * * 0"), [Sun]);
+        assert_eq!(weekdays("0 9 * * 7"), [Sun]);
+        assert_eq!(weekdays("0 9 * * 5-7"), [Sun, Fri, Sat]);
+        assert_eq!(weekdays("0 9 * * 1,3"), [Mon, Wed]);
+    }
+
+    #[test]
+    fn weekday_steps_and_names() {
+        assert_eq!(weekdays("0 9 * * */3"), [Sun, Wed, Sat]);
+        assert_eq!(weekdays("0 9 * * 1/2"), [Mon, Wed, Fri]);
+        assert_eq!(weekdays("0 9 * * MON-FRI"), [Mon, Tue, Wed, Thu, Fri]);
+        assert_eq!(weekdays("0 0 9 * * 6"), [Sat]);
+    }
+
+    #[test]
+    fn times_of_day() {
+        let slots: PublicationSlots = "12:30,18:45".parse().unwrap();
+        let at = Berlin.with_ymd_and_hms(2026, 10, 18, 13, 0, 0).unwrap();
+
+        assert_eq!(
+            slots.next_after(at),
+            Berlin.with_ymd_and_hms(2026, 10, 18, 18, 45, 0).single()
+        );
+        assert_eq!(
+            slots.next_after(Berlin.with_ymd_and_hms(2026, 10, 18, 19, 0, 0).unwrap()),
+            Berlin.with_ymd_and_hms(2026, 10, 19, 12, 30, 0).single()
+        );
+    }
+
+    #[test]
+    fn spaced_times_of_day() {
+        let slots: PublicationSlots = " 12:30, 18:45 ;9:00 ".parse().unwrap();
+        let at = Berlin.with_ymd_and_hms(2026, 10, 18, 13, 0, 0).unwrap();
+
+        assert_eq!(
+            slots.next_after(at),
+            Berlin.with_ymd_and_hms(2026, 10, 18, 18, 45, 0).single()
+        );
+        assert!("12:30, 18:45 20:00".parse::<PublicationSlots>().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_weekdays() {
+        assert!("0 9 * * 8".parse::<PublicationSlots>().is_err());
+        assert!("0 9 * * 5-1".parse::<PublicationSlots>().is_err());
+        assert!("0 9 * * */0".parse::<PublicationSlots>().is_err());
+    }
+}
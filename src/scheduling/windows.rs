@@ -0,0 +1,243 @@
+use super::localize;
+use chrono::{DateTime, Datelike, Days, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+/// A daily time range. An `end` at or before `start` means the window
+/// crosses midnight and closes on the following day.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.trim().split_once('-') else {
+            anyhow::bail!("time window must look like HH:MM-HH:MM, got {s:?}");
+        };
+        Ok(Self {
+            start: NaiveTime::parse_from_str(start.trim(), "%H:%M")?,
+            end: NaiveTime::parse_from_str(end.trim(), "%H:%M")?,
+        })
+    }
+}
+
+/// Posting windows for every weekday. A window belongs to the day it opens on.
+///
+/// Format: `;`-separated entries of `[days=]HH:MM-HH:MM[,HH:MM-HH:MM...]`,
+/// where `days` is a `,`-separated list of weekdays or ranges (`mon-fri`).
+/// Entries without days apply to the whole week, e.g.
+/// `mon-fri=08:00-01:00;sat,sun=10:00-02:00`.
+#[derive(Debug, Clone, Default)]
+pub struct PostingWindows {
+    days: [Vec<TimeWindow>; 7],
+}
+
+impl PostingWindows {
+    /// Returns the moment the currently open window closes, or `None` if no
+    /// window is open at `now`.
+    pub fn current_window_end(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.occurrences(now)
+            .filter(|(start, end)| *start <= now && now < *end)
+            .map(|(_, end)| end)
+            .max()
+    }
+
+    /// Returns the moment the next window opens after `now`.
+    pub fn next_window_start(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.occurrences(now)
+            .map(|(start, _)| start)
+            .filter(|start| *start > now)
+            .min()
+    }
+
+    fn occurrences(&self, now: DateTime<Tz>) -> impl Iterator<Item = (DateTime<Tz>, DateTime<Tz>)> {
+        let tz = now.timezone();
+        let yesterday = now.date_naive() - Days::new(1);
+        (0..9).flat_map(move |offset| {
+            let date = yesterday + Days::new(offset);
+            self.days[date.weekday().num_days_from_monday() as usize]
+                .iter()
+                .map(move |window| {
+                    let end_date = if window.end <= window.start {
+                        date + Days::new(1)
+                    } else {
+                        date
+                    };
+                    (
+                        localize(&tz, date.and_time(window.start)),
+                        localize(&tz, end_date.and_time(window.end)),
+                    )
+                })
+        })
+    }
+}
+
+fn parse_weekdays(s: &str) -> anyhow::Result<Vec<Weekday>> {
+    let mut result = vec![];
+    for part in s.split(',') {
+        match part.trim().split_once('-') {
+            Some((from, to)) => {
+                let mut day = Weekday::from_str(from.trim())
+                    .map_err(|_| anyhow::anyhow!("invalid weekday {from:?}"))?;
+                let to = Weekday::from_str(to.trim())
+                    .map_err(|_| anyhow::anyhow!("invalid weekday {to:?}"))?;
+                result.push(day);
+                while day != to {
+                    day = day.succ();
+                    result.push(day);
+                }
+            }
+            None => result.push(
+                Weekday::from_str(part.trim())
+                    .map_err(|_| anyhow::anyhow!("invalid weekday {part:?}"))?,
+            ),
+        }
+    }
+    Ok(result)
+}
+
+impl FromStr for PostingWindows {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = Self::default();
+        for entry in s.split(';').map(str::trim).filter(|v| !v.is_empty()) {
+            let (days, windows) = match entry.split_once('=') {
+                Some((days, windows)) => (parse_weekdays(days)?, windows),
+                None => (
+                    (0..7).map(|v| Weekday::try_from(v).unwrap()).collect(),
+                    entry,
+                ),
+            };
+            let windows = windows
+                .split(',')
+                .map(TimeWindow::from_str)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            for day in days {
+                result.days[day.num_days_from_monday() as usize].extend(windows.iter().copied());
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// When posts go out without a notification: always, never, or during
+/// posting windows, written as `always`, `never` or in the
+/// [`PostingWindows`] format.
+#[derive(Debug, Clone)]
+pub enum SilentHours {
+    Always,
+    Never,
+    Windows(PostingWindows),
+}
+
+impl SilentHours {
+    pub fn contains(&self, at: DateTime<Tz>) -> bool {
+        match self {
+            SilentHours::Always => true,
+            SilentHours::Never => false,
+            SilentHours::Windows(windows) => windows.current_window_end(at).is_some(),
+        }
+    }
+}
+
+impl FromStr for SilentHours {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "always" => Ok(SilentHours::Always),
+            "never" => Ok(SilentHours::Never),
+            _ => Ok(SilentHours::Windows(s.parse()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeDelta, TimeZone};
+    use chrono_tz::Europe::Berlin;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        let date = NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        localize(&Berlin, date.and_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn overnight_window_closes_next_day() {
+        let windows: PostingWindows = "mon=22:00-02:00".parse().unwrap();
+
+        assert_eq!(
+            windows.current_window_end(at(19, 23, 0)),
+            Some(at(20, 2, 0))
+        );
+        assert_eq!(windows.current_window_end(at(20, 1, 0)), Some(at(20, 2, 0)));
+        assert_eq!(windows.current_window_end(at(20, 2, 0)), None);
+        assert_eq!(windows.next_window_start(at(20, 3, 0)), Some(at(26, 22, 0)));
+    }
+
+    #[test]
+    fn day_ranges_and_lists() {
+        let windows: PostingWindows = "mon-fri=08:00-18:00;sat,sun=10:00-12:00".parse().unwrap();
+
+        // 2026-10-23 is a Friday.
+        assert_eq!(
+            windows.current_window_end(at(23, 17, 0)),
+            Some(at(23, 18, 0))
+        );
+        assert_eq!(windows.current_window_end(at(24, 9, 0)), None);
+        assert_eq!(windows.next_window_start(at(24, 9, 0)), Some(at(24, 10, 0)));
+        assert_eq!(windows.next_window_start(at(25, 12, 0)), Some(at(26, 8, 0)));
+    }
+
+    #[test]
+    fn day_range_wraps_around_the_week() {
+        let windows: PostingWindows = "fri-mon=10:00-11:00".parse().unwrap();
+
+        for day in [23, 24, 25, 26] {
+            assert!(windows.current_window_end(at(day, 10, 30)).is_some());
+        }
+        for day in [20, 21, 22] {
+            assert!(windows.current_window_end(at(day, 10, 30)).is_none());
+        }
+    }
+
+    #[test]
+    fn windows_without_days_apply_every_day() {
+        let windows: PostingWindows = "09:00-10:00,20:00-21:00".parse().unwrap();
+
+        for day in 19..=25 {
+            assert!(windows.current_window_end(at(day, 9, 30)).is_some());
+            assert!(windows.current_window_end(at(day, 20, 30)).is_some());
+            assert!(windows.current_window_end(at(day, 15, 0)).is_none());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_windows() {
+        assert!("mon=8-9".parse::<PostingWindows>().is_err());
+        assert!("someday=08:00-09:00".parse::<PostingWindows>().is_err());
+        assert!("08:00".parse::<PostingWindows>().is_err());
+    }
+
+    #[test]
+    fn window_opening_in_dst_gap_starts_after_it() {
+        // Clocks in Berlin jump from 02:00 to 03:00 on 2026-03-29.
+        let windows: PostingWindows = "sun=02:30-04:00".parse().unwrap();
+        let before = Berlin.with_ymd_and_hms(2026, 3, 29, 1, 0, 0).unwrap();
+        let start = Berlin.with_ymd_and_hms(2026, 3, 29, 3, 30, 0).unwrap();
+
+        assert_eq!(windows.next_window_start(before), Some(start));
+        assert!(windows.current_window_end(start).is_some());
+        assert!(
+            windows
+                .current_window_end(start - TimeDelta::minutes(1))
+                .is_none()
+        );
+    }
+}
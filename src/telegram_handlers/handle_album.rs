@@ -0,0 +1,227 @@
+use crate::{
+    captions::caption_control,
+    database::{Database, MediaType, NewPost, NewPostItem},
+    telegram_handlers::{clip_fingerprint, new_post, photo_hash, report_duplicate, resolve_target},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use teloxide::{prelude::*, types::ReactionType};
+use tokio::sync::Mutex;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// How long to wait for further items of an album after the last one arrived.
+const ALBUM_SETTLE_DELAY: Duration = Duration::from_secs(2);
+
+/// Album items received so far, keyed by media group id. Telegram delivers
+/// every item of an album as a separate message.
+#[derive(Default)]
+pub struct Albums {
+    pending: Mutex<HashMap<String, PendingAlbum>>,
+    /// Timers of the pending albums, see [`Albums::flush`].
+    timers: TaskTracker,
+}
+
+impl Albums {
+    /// Waits for the timers of pending albums, which store them right away
+    /// once shutdown is requested.
+    pub async fn flush(&self) {
+        self.timers.close();
+        self.timers.wait().await;
+        self.timers.reopen();
+    }
+}
+
+#[derive(Default)]
+struct PendingAlbum {
+    items: Vec<AlbumItem>,
+    /// Bumped with every item, so only the last one's timer stores the album.
+    generation: u64,
+}
+
+struct AlbumItem {
+    message: Message,
+    media_type: MediaType,
+    file_id: String,
+}
+
+/// Photos and videos belonging to an album.
+pub fn album_item(message: Message) -> bool {
+    message.media_group_id().is_some() && (message.photo().is_some() || message.video().is_some())
+}
+
+pub async fn handle_album(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    albums: Arc<Albums>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let Some(group_id) = message.media_group_id().map(str::to_string) else {
+        return Ok(());
+    };
+
+    let item = match (message.photo(), message.video()) {
+        (Some(photo), _) => AlbumItem {
+            media_type: MediaType::Photo,
+            file_id: photo.last().unwrap().file.id.clone(),
+            message,
+        },
+        (None, Some(video)) => AlbumItem {
+            media_type: MediaType::Video,
+            file_id: video.file.id.clone(),
+            message,
+        },
+        (None, None) => return Ok(()),
+    };
+
+    let generation = {
+        let mut pending = albums.pending.lock().await;
+        let album = pending.entry(group_id.clone()).or_default();
+        album.items.push(item);
+        album.generation += 1;
+        album.generation
+    };
+
+    let timers = albums.timers.clone();
+    timers.spawn(async move {
+        // On shutdown the album is stored with the items received so far.
+        shutdown
+            .run_until_cancelled(tokio::time::sleep(ALBUM_SETTLE_DELAY))
+            .await;
+
+        let items = {
+            let mut pending = albums.pending.lock().await;
+            match pending.get(&group_id) {
+                Some(album) if album.generation == generation => {
+                    pending.remove(&group_id).unwrap().items
+                }
+                _ => return,
+            }
+        };
+
+        if let Err(e) = store_album(&bot, &db, items).await {
+            log::error!("Error saving album: {e:?}");
+        }
+    });
+
+    Ok(())
+}
+
+/// Stores the collected items of an album as one post, leaving out photos and
+/// videos already queued or published unless the caption says `force`.
+async fn store_album(bot: &Bot, db: &Database, mut items: Vec<AlbumItem>) -> anyhow::Result<()> {
+    items.sort_by_key(|item| item.message.id.0);
+
+    // The caption of an album is attached to one of its items, usually the
+    // first one.
+    let lead = items
+        .iter()
+        .position(|item| item.message.caption().is_some())
+        .unwrap_or(0);
+    let force = caption_control(&items[lead].message).contains("force");
+    let target_id = resolve_target(db, &items[lead].message).await;
+
+    let mut seen = HashSet::new();
+    let (mut kept, mut kept_items) = (Vec::new(), Vec::new());
+    for item in &items {
+        let (image_hash, file_unique_id, clip_fingerprint) = match item.message.video() {
+            Some(video) => (
+                None,
+                Some(video.file.unique_id.clone()),
+                clip_fingerprint(bot, &item.message).await,
+            ),
+            // A photo that cannot be hashed is kept, unchecked for duplicates.
+            None => match photo_hash(bot, &item.file_id).await {
+                Ok(hash) => (Some(hash), None, None),
+                Err(e) => {
+                    log::error!("Unable to hash album item {}: {e:?}", item.file_id);
+                    (None, None, None)
+                }
+            },
+        };
+
+        if !force {
+            let keys: Vec<String> = [&image_hash, &file_unique_id, &clip_fingerprint]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            if keys.iter().any(|key| seen.contains(key)) {
+                log::warn!("Album item {} repeats within the album", item.file_id);
+                continue;
+            }
+            seen.extend(keys);
+
+            let existing = match (&file_unique_id, &image_hash) {
+                (Some(unique_id), _) => {
+                    db.get_clip_post(unique_id.clone(), clip_fingerprint.clone(), target_id)
+                        .await?
+                }
+                (None, Some(hash)) => db.get_post_by_hash(hash.clone(), target_id).await?,
+                (None, None) => None,
+            };
+            if let Some(post) = existing {
+                log::warn!("Album item {} already exists", item.file_id);
+                let submitted_photo = image_hash.is_some().then_some(item.file_id.as_str());
+                if let Err(e) =
+                    report_duplicate(bot, db, &item.message, submitted_photo, &post).await
+                {
+                    log::error!("Unable to report duplicate album item: {e:?}");
+                }
+                continue;
+            }
+        }
+
+        kept.push(item);
+        kept_items.push(NewPostItem {
+            media_type: item.media_type.clone(),
+            file_id: item.file_id.clone(),
+            image_hash,
+            spoiler: item.message.has_media_spoiler(),
+            file_unique_id,
+            clip_fingerprint,
+        });
+    }
+
+    let Some(first) = kept.first() else {
+        return Ok(());
+    };
+
+    let lead_message = &items[lead].message;
+    let new_post = if kept_items.len() == 1 {
+        let single = kept_items.pop().unwrap();
+        NewPost {
+            image_hash: single.image_hash,
+            file_unique_id: single.file_unique_id,
+            clip_fingerprint: single.clip_fingerprint,
+            spoiler: single.spoiler,
+            ..new_post(db, lead_message, single.media_type, single.file_id).await
+        }
+    } else {
+        NewPost {
+            spoiler: kept_items.iter().any(|item| item.spoiler),
+            items: kept_items,
+            ..new_post(db, lead_message, MediaType::Album, first.file_id.clone()).await
+        }
+    };
+
+    let post = db
+        .create_post(new_post, first.message.chat.id.0, first.message.id.0)
+        .await?;
+    for item in &kept[1..] {
+        db.add_message_id_for_post(post.id, item.message.chat.id.0, item.message.id.0)
+            .await?;
+    }
+    log::info!("Album saved with {} items", kept.len());
+
+    bot.set_message_reaction(lead_message.chat.id, lead_message.id)
+        .reaction(vec![ReactionType::Emoji {
+            emoji: "👍".to_string(),
+        }])
+        .await?;
+
+    Ok(())
+}
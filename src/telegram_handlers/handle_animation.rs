@@ -1,15 +1,43 @@
-use crate::database::{Database, MediaType};
+use crate::{
+    captions::caption_control,
+    database::{Database, MediaType, NewPost},
+    telegram_handlers::{clip_fingerprint, new_post, report_duplicate, resolve_target},
+};
 use std::sync::Arc;
-use teloxide::prelude::*;
+use teloxide::{Bot, prelude::*};
 
-pub async fn handle_animation(message: Message, db: Arc<Database>) -> anyhow::Result<()> {
-    let file_meta = &message.animation().unwrap().file;
+pub async fn handle_animation(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let animation = message.animation().unwrap();
+    let file_meta = &animation.file;
+
+    let fingerprint = clip_fingerprint(&bot, &message).await;
+
+    if !caption_control(&message).contains("force") {
+        let target_id = resolve_target(&db, &message).await;
+        match db
+            .get_clip_post(file_meta.unique_id.clone(), fingerprint.clone(), target_id)
+            .await
+        {
+            Ok(Some(post)) => {
+                log::warn!("Animation {} already exists", file_meta.unique_id);
+                return report_duplicate(&bot, &db, &message, None, &post).await;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Error checking duplicate presence: {e:?}");
+                return Err(e);
+            }
+        }
+    }
+
+    let new_post = new_post(&db, &message, MediaType::Animation, file_meta.id.clone()).await;
 
     let create_post_future = db.create_post(
-        None,
-        MediaType::Animation,
-        file_meta.id.clone(),
-        None,
+        NewPost {
+            clip_fingerprint: fingerprint,
+            file_unique_id: Some(file_meta.unique_id.clone()),
+            ..new_post
+        },
         message.chat.id.0,
         message.id.0,
     );
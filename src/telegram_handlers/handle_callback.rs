@@ -0,0 +1,124 @@
+use crate::{config::Config, database::Database};
+use std::{str::FromStr, sync::Arc};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+use uuid::Uuid;
+
+enum CallbackAction {
+    Delete(Uuid),
+    Undo(Uuid),
+}
+
+impl FromStr for CallbackAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((action, post_id)) = s.split_once(' ') else {
+            anyhow::bail!("malformed callback payload: {s:?}");
+        };
+        let post_id = Uuid::from_str(post_id)?;
+        match action {
+            "del" => Ok(CallbackAction::Delete(post_id)),
+            "undo" => Ok(CallbackAction::Undo(post_id)),
+            _ => anyhow::bail!("unknown callback action: {action:?}"),
+        }
+    }
+}
+
+pub fn post_keyboard(post_id: Uuid, deleted: bool) -> InlineKeyboardMarkup {
+    let button = if deleted {
+        InlineKeyboardButton::callback("Deleted (Undo)", format!("undo {post_id}"))
+    } else {
+        InlineKeyboardButton::callback("Delete", format!("del {post_id}"))
+    };
+    InlineKeyboardMarkup::new(vec![vec![button]])
+}
+
+pub async fn handle_callback(
+    bot: Bot,
+    query: CallbackQuery,
+    db: Arc<Database>,
+    cfg: Config,
+) -> anyhow::Result<()> {
+    // Buttons are only attached in allowed chats, which may be groups, so the
+    // chat is checked like for messages rather than the pressing user.
+    let allowed = query
+        .message
+        .as_ref()
+        .is_some_and(|message| cfg.allowed_sender_chats.contains(&message.chat().id.0));
+    if !allowed {
+        bot.answer_callback_query(query.id)
+            .text("You are not allowed to do that")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    let action = match query.data.as_deref().map(CallbackAction::from_str) {
+        Some(Ok(action)) => action,
+        Some(Err(e)) => {
+            log::warn!("Unable to parse callback query: {e:?}");
+            bot.answer_callback_query(query.id)
+                .text("Unknown action")
+                .await?;
+            return Ok(());
+        }
+        None => {
+            bot.answer_callback_query(query.id).await?;
+            return Ok(());
+        }
+    };
+
+    let (post_id, deleted) = match action {
+        CallbackAction::Delete(post_id) => (post_id, true),
+        CallbackAction::Undo(post_id) => (post_id, false),
+    };
+
+    match db.fetch_post(post_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            bot.answer_callback_query(query.id)
+                .text("Post was not found")
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            log::error!("failed to fetch post: {e:?}");
+            bot.answer_callback_query(query.id)
+                .text("Database error")
+                .await?;
+            return Err(e);
+        }
+    }
+
+    let result = if deleted {
+        db.delete_post(post_id).await
+    } else {
+        db.restore_post(post_id).await
+    };
+    if let Err(e) = result {
+        log::error!("failed to update post: {e:?}");
+        bot.answer_callback_query(query.id)
+            .text("Database error")
+            .await?;
+        return Err(e);
+    }
+
+    bot.answer_callback_query(query.id)
+        .text(if deleted {
+            "Post deleted"
+        } else {
+            "Post restored"
+        })
+        .await?;
+
+    if let Some(message) = &query.message {
+        bot.edit_message_reply_markup(message.chat().id, message.id())
+            .reply_markup(post_keyboard(post_id, deleted))
+            .await?;
+    }
+
+    Ok(())
+}
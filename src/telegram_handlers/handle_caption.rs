@@ -0,0 +1,86 @@
+use crate::{
+    database::Database,
+    telegram_handlers::fetch_replied_post,
+    utils::{CAPTION_MAX_LENGTH, telegram_len},
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+const USAGE: &str = "Usage: /caption <template | off | reset> in reply to a post, \
+    or /caption <target> <template | off | reset>";
+
+/// `off` publishes without a caption, `reset` falls back to the next template
+/// in line.
+fn parse_template(value: &str) -> Option<String> {
+    match value.trim() {
+        "off" => Some(String::new()),
+        "reset" => None,
+        template => Some(template.to_string()),
+    }
+}
+
+pub async fn handle_caption(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    args: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    if args.trim().is_empty() {
+        bot.send_message(message.chat.id, USAGE)
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    if telegram_len(&args) > CAPTION_MAX_LENGTH {
+        bot.send_message(
+            message.chat.id,
+            format!("Template is too long, {CAPTION_MAX_LENGTH} characters at most"),
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    }
+
+    if message.reply_to_message().is_some() {
+        let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+            return Ok(());
+        };
+
+        db.set_post_caption_template(post.id, parse_template(&args))
+            .await?;
+
+        bot.send_message(message.chat.id, "Post caption updated")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    let Some((name, template)) = args.trim().split_once(char::is_whitespace) else {
+        bot.send_message(message.chat.id, USAGE)
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    let Some(target) = db.fetch_target_by_name(name).await? else {
+        bot.send_message(message.chat.id, "Target was not found, see /targets")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    db.set_target_caption_template(target.id, parse_template(template))
+        .await?;
+
+    bot.send_message(
+        message.chat.id,
+        format!("Caption of {} updated", target.name),
+    )
+    .reply_parameters(reply_parameters)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use crate::{
+    captions::caption_control,
+    config::{Config, DocumentImages},
+    database::{Database, MediaType, NewPost},
+    telegram_handlers::{download, new_post, report_duplicate, resolve_target},
+    utils::{image_hash, normalize_photo},
+};
+use std::sync::Arc;
+use teloxide::{
+    Bot,
+    prelude::*,
+    types::{InputFile, ReactionType, ReplyParameters},
+};
+
+/// Image formats accepted when sent as a file.
+const IMAGE_MIME_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+/// Largest file the Bot API lets bots download.
+const MAX_DOWNLOAD_SIZE: u32 = 20 * 1024 * 1024;
+
+/// Images sent as uncompressed documents.
+pub fn image_document(message: Message) -> bool {
+    message
+        .document()
+        .and_then(|document| document.mime_type.as_ref())
+        .is_some_and(|mime| IMAGE_MIME_TYPES.contains(&mime.essence_str()))
+}
+
+pub async fn handle_document(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let file_meta = &message.document().unwrap().file;
+    if file_meta.size > MAX_DOWNLOAD_SIZE {
+        bot.send_message(message.chat.id, "Image is too large, 20 MB at most")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    let image = download(&bot, &file_meta.id).await?;
+    let hash = image_hash(&image)?;
+
+    if !caption_control(&message).contains("force") {
+        let target_id = resolve_target(&db, &message).await;
+        match db.get_post_by_hash(hash.clone(), target_id).await {
+            Ok(Some(post)) => {
+                log::warn!("Hash {hash} already exists");
+                return report_duplicate(&bot, &db, &message, None, &post).await;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Error checking hash presence: {e:?}");
+                return Err(e);
+            }
+        }
+    }
+
+    // A document's file id cannot be sent as a photo, so the converted photo
+    // is uploaded in reply to obtain one.
+    let converted = match cfg.document_images {
+        DocumentImages::Photo => {
+            let photo = normalize_photo(&image)?;
+            let msg = bot
+                .send_photo(message.chat.id, InputFile::memory(photo))
+                .caption("Converted to photo")
+                .reply_parameters(reply_parameters)
+                .await?;
+            let file_id = msg.photo().unwrap().last().unwrap().file.id.clone();
+            Some((msg, file_id))
+        }
+        DocumentImages::Document => None,
+    };
+
+    let new_post = match &converted {
+        Some((_, file_id)) => new_post(&db, &message, MediaType::Photo, file_id.clone()).await,
+        None => new_post(&db, &message, MediaType::Document, file_meta.id.clone()).await,
+    };
+
+    let create_post_future = db.create_post(
+        NewPost {
+            image_hash: Some(hash.clone()),
+            ..new_post
+        },
+        message.chat.id.0,
+        message.id.0,
+    );
+    match create_post_future.await {
+        Ok(post) => {
+            log::info!("Post saved");
+
+            if let Some((msg, _)) = converted
+                && let Err(e) = db
+                    .add_message_id_for_post(post.id, msg.chat.id.0, msg.id.0)
+                    .await
+            {
+                log::error!("Error saving post message id: {e:?}");
+            }
+
+            bot.set_message_reaction(message.chat.id, message.id)
+                .reaction(vec![ReactionType::Emoji {
+                    emoji: "👍".to_string(),
+                }])
+                .await?;
+        }
+        Err(e) => {
+            log::error!("Error saving post: {e:?}");
+        }
+    };
+
+    Ok(())
+}
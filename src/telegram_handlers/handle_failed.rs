@@ -0,0 +1,84 @@
+use crate::{
+    database::Database,
+    telegram_handlers::fetch_replied_post,
+    utils::{MESSAGE_MAX_LENGTH, telegram_len},
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+use uuid::Uuid;
+
+/// Errors are cut to this many characters, so more posts fit in the list.
+const ERROR_MAX_CHARS: usize = 200;
+
+pub async fn handle_failed(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let posts = db.fetch_failed_posts().await?;
+
+    let text = if posts.is_empty() {
+        "No failed posts".to_string()
+    } else {
+        let mut text = String::from("Failed posts:");
+        for (i, post) in posts.iter().enumerate() {
+            let error = post.last_error.as_deref().unwrap_or("unknown error");
+            let error = match error.char_indices().nth(ERROR_MAX_CHARS) {
+                Some((end, _)) => format!("{}…", &error[..end]),
+                None => error.to_string(),
+            };
+            let line = format!(
+                "\n{} ({:?}, {} attempts): {error}",
+                post.id, post.media_type, post.attempts,
+            );
+
+            let more = format!("\n…and {} more", posts.len() - i);
+            if telegram_len(&text) + telegram_len(&line) + telegram_len(&more) > MESSAGE_MAX_LENGTH
+            {
+                text.push_str(&more);
+                break;
+            }
+            text.push_str(&line);
+        }
+        text
+    };
+
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+/// `/requeue [all | <post id>]`, or a reply to the post to requeue.
+pub async fn handle_requeue(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    args: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let ids = match args.trim() {
+        "all" => None,
+        "" => match fetch_replied_post(&bot, &message, &db).await? {
+            Some(post) => Some(vec![post.id]),
+            None => return Ok(()),
+        },
+        value => match value.parse::<Uuid>() {
+            Ok(post_id) => Some(vec![post_id]),
+            Err(_) => {
+                bot.send_message(
+                    message.chat.id,
+                    "Usage: /requeue [all | <post id>], or reply to a post",
+                )
+                .reply_parameters(reply_parameters)
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let count = db.requeue_failed_posts(ids).await?;
+    bot.send_message(message.chat.id, format!("Requeued {count} post(s)"))
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
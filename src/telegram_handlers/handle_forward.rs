@@ -0,0 +1,46 @@
+use crate::database::{Database, Post};
+use std::sync::Arc;
+use teloxide::{
+    prelude::*,
+    types::{MessageOrigin, ReplyParameters},
+};
+
+/// Resolves a message forwarded from a target channel to the post it published.
+pub async fn published_post(message: Message, db: Arc<Database>) -> Option<Post> {
+    let Some(MessageOrigin::Channel {
+        chat, message_id, ..
+    }) = message.forward_origin()
+    else {
+        return None;
+    };
+
+    match db.fetch_post_by_message_id(chat.id.0, message_id.0).await {
+        Ok(post) => post,
+        Err(e) => {
+            log::error!("failed to fetch post: {e:?}");
+            None
+        }
+    }
+}
+
+/// Links the forward to its post so that replies to it work like replies to
+/// the original submission.
+pub async fn handle_published_forward(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    post: Post,
+) -> anyhow::Result<()> {
+    db.add_message_id_for_post(post.id, message.chat.id.0, message.id.0)
+        .await?;
+
+    let text = match post.sent_datetime {
+        Some(sent_datetime) => format!("Published at {}", sent_datetime.and_utc().to_rfc3339()),
+        None => "Post is not published yet".to_string(),
+    };
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
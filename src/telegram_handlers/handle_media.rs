@@ -0,0 +1,55 @@
+use crate::{
+    database::{Database, MediaType},
+    telegram_handlers::{new_post, refuse_oversized},
+};
+use std::sync::Arc;
+use teloxide::prelude::*;
+
+async fn queue_media(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    media_type: MediaType,
+    file_id: String,
+) -> anyhow::Result<()> {
+    let new_post = new_post(&db, &message, media_type, file_id).await;
+    if refuse_oversized(&bot, &message, &new_post).await? {
+        return Ok(());
+    }
+
+    let create_post_future = db.create_post(new_post, message.chat.id.0, message.id.0);
+    match create_post_future.await {
+        Ok(_) => {
+            log::info!("Post saved");
+        }
+        Err(e) => {
+            log::error!("Error saving post: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_audio(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let file_id = message.audio().unwrap().file.id.clone();
+    queue_media(bot, message, db, MediaType::Audio, file_id).await
+}
+
+pub async fn handle_voice(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let file_id = message.voice().unwrap().file.id.clone();
+    queue_media(bot, message, db, MediaType::Voice, file_id).await
+}
+
+pub async fn handle_sticker(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let file_id = message.sticker().unwrap().file.id.clone();
+    queue_media(bot, message, db, MediaType::Sticker, file_id).await
+}
+
+pub async fn handle_video_note(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+) -> anyhow::Result<()> {
+    let file_id = message.video_note().unwrap().file.id.clone();
+    queue_media(bot, message, db, MediaType::VideoNote, file_id).await
+}
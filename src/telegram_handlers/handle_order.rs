@@ -0,0 +1,46 @@
+use crate::{
+    config::Config,
+    database::{Database, QUEUE_ORDER_SETTING, QueueOrder},
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+pub async fn handle_order(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    order: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    if order.trim().is_empty() {
+        let current = db.queue_order(cfg.queue_order).await?;
+        bot.send_message(
+            message.chat.id,
+            format!("Queue order: {current} (available: random, fifo, lifo, shuffle-bag)"),
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    }
+
+    let order = match order.parse::<QueueOrder>() {
+        Ok(order) => order,
+        Err(e) => {
+            bot.send_message(message.chat.id, e.to_string())
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    db.set_setting(QUEUE_ORDER_SETTING, order.to_string())
+        .await?;
+
+    bot.send_message(message.chat.id, format!("Queue order set to {order}"))
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::{
+    config::Config,
+    database::{Database, Pause},
+};
+use chrono::Utc;
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+/// `/pause [duration]`, pausing indefinitely without a duration.
+pub async fn handle_pause(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    duration: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let pause = match duration.trim() {
+        "" => Pause::Indefinitely,
+        value => match humantime::parse_duration(value) {
+            Ok(duration) => Pause::Until(Utc::now() + duration),
+            Err(e) => {
+                bot.send_message(message.chat.id, format!("Invalid duration: {e}"))
+                    .reply_parameters(reply_parameters)
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    db.set_pause(Some(pause)).await?;
+
+    let text = match pause {
+        Pause::Indefinitely => "Publishing paused until /resume".to_string(),
+        Pause::Until(until) => format!(
+            "Publishing paused until {}",
+            until.with_timezone(&cfg.timezone).format("%Y-%m-%d %H:%M")
+        ),
+    };
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_resume(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let text = match db.pause().await? {
+        Some(_) => {
+            db.set_pause(None).await?;
+            "Publishing resumed"
+        }
+        None => "Publishing is not paused",
+    };
+
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::{
+    config::Config,
+    database::{Database, PRIORITY_HIGH, PRIORITY_LOW, PRIORITY_NEXT, PRIORITY_NORMAL},
+    telegram_handlers::fetch_replied_post,
+    workers::publish_now,
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+use tokio_util::sync::CancellationToken;
+
+pub async fn handle_next(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    db.set_post_priority(post.id, PRIORITY_NEXT).await?;
+
+    bot.send_message(message.chat.id, "Post will be sent at the next slot")
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_priority(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    level: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let priority = match level.trim().to_lowercase().as_str() {
+        "high" => PRIORITY_HIGH,
+        "normal" => PRIORITY_NORMAL,
+        "low" => PRIORITY_LOW,
+        _ => {
+            bot.send_message(message.chat.id, "Usage: /priority high|normal|low")
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    db.set_post_priority(post.id, priority).await?;
+
+    bot.send_message(message.chat.id, "Priority updated")
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn handle_send_now(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    if post.is_sent {
+        bot.send_message(message.chat.id, "Post was already sent")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    match publish_now(bot.clone(), &db, &cfg, post, &shutdown).await {
+        Ok(_) => {
+            bot.send_message(message.chat.id, "Post sent")
+                .reply_parameters(reply_parameters)
+                .await?;
+        }
+        Err(e) => {
+            log::error!("Error sending post: {e:?}");
+            bot.send_message(message.chat.id, format!("Unable to send post: {e}"))
+                .reply_parameters(reply_parameters)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
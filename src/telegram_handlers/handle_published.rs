@@ -0,0 +1,252 @@
+use crate::{
+    captions::{caption_limit, render_caption, suffix_entities},
+    config::Config,
+    database::{Database, MediaType, Post, Target},
+    telegram_handlers::fetch_replied_post,
+    utils::telegram_len,
+    workers::Commands,
+};
+use std::sync::Arc;
+use teloxide::{
+    RequestError,
+    prelude::*,
+    types::{MessageId, ReplyParameters},
+};
+
+/// What a command did to a post to report back, or why it was refused.
+type Outcome = Result<String, String>;
+
+/// The target a post is published to, or why it cannot be acted on as a
+/// published post.
+async fn published_target(db: &Database, post: &Post) -> anyhow::Result<Result<Target, String>> {
+    if !post.is_published() {
+        return Ok(Err("Post is not published".to_string()));
+    }
+
+    match db.fetch_target(post.target_id).await? {
+        Some(target) => Ok(Ok(target)),
+        None => anyhow::bail!("target {} does not exist", post.target_id),
+    }
+}
+
+async fn reply_outcome(bot: &Bot, message: &Message, outcome: Outcome) -> anyhow::Result<()> {
+    let (Ok(text) | Err(text)) = outcome;
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+    Ok(())
+}
+
+async fn unpost(bot: &Bot, db: &Database, post: Post) -> anyhow::Result<Outcome> {
+    let target = match published_target(db, &post).await? {
+        Ok(target) => target,
+        Err(refusal) => return Ok(Err(refusal)),
+    };
+
+    for message_id in db.fetch_post_message_ids(post.id, target.chat_id).await? {
+        if let Err(e) = bot
+            .delete_message(ChatId(target.chat_id), MessageId(message_id))
+            .await
+        {
+            log::error!("failed to delete channel message {message_id}: {e:?}");
+        }
+    }
+    db.mark_post_unpublished(post.id, target.chat_id).await?;
+
+    Ok(Ok(format!("Post removed from {}", target.name)))
+}
+
+/// Replaces the contributor caption of a published post with the arguments
+/// of the `/recaption` `message`, and edits the channel messages to match.
+async fn recaption(
+    bot: &Bot,
+    db: &Database,
+    cfg: &Config,
+    post: Post,
+    message: &Message,
+    text: &str,
+) -> anyhow::Result<Outcome> {
+    let target = match published_target(db, &post).await? {
+        Ok(target) => target,
+        Err(refusal) => return Ok(Err(refusal)),
+    };
+
+    let text = text.trim();
+    let limit = caption_limit(&post.media_type);
+    match post.media_type {
+        MediaType::Sticker | MediaType::VideoNote => {
+            return Ok(Err("This post cannot have a caption".to_string()));
+        }
+        MediaType::Text if text.is_empty() => {
+            return Ok(Err("Text post cannot be empty".to_string()));
+        }
+        _ if telegram_len(text) > limit => {
+            return Ok(Err(format!(
+                "Caption is too long, {limit} characters at most"
+            )));
+        }
+        _ => {}
+    }
+
+    let caption = (!text.is_empty()).then(|| text.to_string());
+    let entities = suffix_entities(
+        message.text().unwrap_or("").trim_end(),
+        message.entities().unwrap_or_default(),
+        text,
+    );
+    let caption_entities = Some(entities)
+        .filter(|entities| !entities.is_empty())
+        .and_then(|entities| serde_json::to_string(&entities).ok());
+    let published_at = post
+        .sent_datetime
+        .unwrap_or_default()
+        .and_utc()
+        .with_timezone(&cfg.timezone);
+    let previous = render_caption(cfg, &target, &post, published_at);
+    let post = Post {
+        caption,
+        caption_entities,
+        ..post
+    };
+    let rendered = render_caption(cfg, &target, &post, published_at);
+
+    let mut message_ids = db.fetch_post_message_ids(post.id, target.chat_id).await?;
+    // Albums carry their caption on the first item only.
+    if post.media_type == MediaType::Album {
+        message_ids.sort();
+        message_ids.truncate(1);
+    }
+
+    // The channel is edited first, so a failed edit leaves the stored caption
+    // matching what subscribers see.
+    let chat_id = ChatId(target.chat_id);
+    for (i, message_id) in message_ids.iter().enumerate() {
+        let message_id = MessageId(*message_id);
+        let result = edit_caption(bot, cfg, &post.media_type, chat_id, message_id, &rendered).await;
+        if let Err(e) = result {
+            log::error!("failed to edit channel message {}: {e:?}", message_id.0);
+            for edited in &message_ids[..i] {
+                let edited = MessageId(*edited);
+                if let Err(e) =
+                    edit_caption(bot, cfg, &post.media_type, chat_id, edited, &previous).await
+                {
+                    log::error!("failed to restore channel message {}: {e:?}", edited.0);
+                }
+            }
+            return Err(e.into());
+        }
+    }
+
+    db.set_post_caption(post.id, post.caption, post.caption_entities)
+        .await?;
+
+    Ok(Ok("Caption updated".to_string()))
+}
+
+async fn edit_caption(
+    bot: &Bot,
+    cfg: &Config,
+    media_type: &MediaType,
+    chat_id: ChatId,
+    message_id: MessageId,
+    rendered: &Option<String>,
+) -> Result<Message, RequestError> {
+    match (media_type, rendered.clone()) {
+        (MediaType::Text, Some(rendered)) => {
+            bot.edit_message_text(chat_id, message_id, rendered)
+                .parse_mode(cfg.caption_parse_mode)
+                .await
+        }
+        (_, Some(rendered)) => {
+            bot.edit_message_caption(chat_id, message_id)
+                .caption(rendered)
+                .parse_mode(cfg.caption_parse_mode)
+                .await
+        }
+        (_, None) => bot.edit_message_caption(chat_id, message_id).await,
+    }
+}
+
+async fn repost(db: &Database, post: Post) -> anyhow::Result<Outcome> {
+    if !post.is_sent {
+        return Ok(Err("Post is already queued".to_string()));
+    }
+
+    let Some(target) = db.fetch_target(post.target_id).await? else {
+        anyhow::bail!("target {} does not exist", post.target_id);
+    };
+    db.requeue_sent_post(post.id, target.chat_id).await?;
+
+    Ok(Ok("Post is back in the queue".to_string()))
+}
+
+pub async fn handle_unpost(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    let outcome = unpost(&bot, &db, post).await?;
+    reply_outcome(&bot, &message, outcome).await
+}
+
+pub async fn handle_recaption(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    text: String,
+) -> anyhow::Result<()> {
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    let outcome = recaption(&bot, &db, &cfg, post, &message, &text).await?;
+    reply_outcome(&bot, &message, outcome).await
+}
+
+pub async fn handle_repost(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    let outcome = repost(&db, post).await?;
+    reply_outcome(&bot, &message, outcome).await
+}
+
+/// Commands posted in a target channel in reply to one of its published posts.
+/// Answering there would be public, so the command message is removed once it
+/// succeeds and the outcome is only logged.
+pub async fn handle_channel_command(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    command: Commands,
+) -> anyhow::Result<()> {
+    let Some(reply) = message.reply_to_message() else {
+        return Ok(());
+    };
+    let Some(post) = db
+        .fetch_post_by_message_id(reply.chat.id.0, reply.id.0)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let outcome = match command {
+        Commands::Unpost => unpost(&bot, &db, post).await?,
+        Commands::Recaption(text) => recaption(&bot, &db, &cfg, post, &message, &text).await?,
+        Commands::Repost => repost(&db, post).await?,
+        _ => return Ok(()),
+    };
+
+    match outcome {
+        Ok(done) => {
+            log::info!("{done}, as asked in chat {}", message.chat.id);
+            bot.delete_message(message.chat.id, message.id).await?;
+        }
+        Err(refusal) => log::warn!("command in chat {} refused: {refusal}", message.chat.id),
+    }
+
+    Ok(())
+}
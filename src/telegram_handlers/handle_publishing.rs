@@ -0,0 +1,150 @@
+use crate::{database::Database, scheduling::SilentHours, telegram_handlers::fetch_replied_post};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+const SILENT_USAGE: &str = "Usage: /silent <on | off | reset> in reply to a post, \
+    or /silent <target> <hours | always | never | reset>, hours like 23:00-09:00";
+
+const PROTECT_USAGE: &str = "Usage: /protect <on | off | reset> in reply to a post, \
+    or /protect <target> <on | off | reset>";
+
+/// `reset` falls back to the next policy in line.
+fn parse_switch(value: &str) -> Option<Option<bool>> {
+    match value.trim() {
+        "on" => Some(Some(true)),
+        "off" => Some(Some(false)),
+        "reset" => Some(None),
+        _ => None,
+    }
+}
+
+/// `reset` falls back to the configured silent hours.
+fn parse_silent_hours(value: &str) -> anyhow::Result<Option<String>> {
+    match value.trim() {
+        "reset" => Ok(None),
+        hours => {
+            hours.parse::<SilentHours>()?;
+            Ok(Some(hours.to_string()))
+        }
+    }
+}
+
+pub async fn handle_silent(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    args: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    if message.reply_to_message().is_some() {
+        let Some(value) = parse_switch(&args) else {
+            bot.send_message(message.chat.id, SILENT_USAGE)
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        };
+        let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+            return Ok(());
+        };
+
+        db.set_post_silent(post.id, value).await?;
+
+        bot.send_message(message.chat.id, "Post notification updated")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    let Some((name, hours)) = args.trim().split_once(char::is_whitespace) else {
+        bot.send_message(message.chat.id, SILENT_USAGE)
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    let hours = match parse_silent_hours(hours) {
+        Ok(hours) => hours,
+        Err(e) => {
+            bot.send_message(message.chat.id, format!("{e}\n\n{SILENT_USAGE}"))
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let Some(target) = db.fetch_target_by_name(name).await? else {
+        bot.send_message(message.chat.id, "Target was not found, see /targets")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    db.set_target_silent_hours(target.id, hours).await?;
+
+    bot.send_message(
+        message.chat.id,
+        format!("Silent hours of {} updated", target.name),
+    )
+    .reply_parameters(reply_parameters)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn handle_protect(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    args: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    if message.reply_to_message().is_some() {
+        let Some(value) = parse_switch(&args) else {
+            bot.send_message(message.chat.id, PROTECT_USAGE)
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        };
+        let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+            return Ok(());
+        };
+
+        db.set_post_protect_content(post.id, value).await?;
+
+        bot.send_message(message.chat.id, "Post content protection updated")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    let Some((name, value)) = args
+        .trim()
+        .split_once(char::is_whitespace)
+        .and_then(|(name, value)| Some((name, parse_switch(value)?)))
+    else {
+        bot.send_message(message.chat.id, PROTECT_USAGE)
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    let Some(target) = db.fetch_target_by_name(name).await? else {
+        bot.send_message(message.chat.id, "Target was not found, see /targets")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    };
+
+    db.set_target_protect_content(target.id, value).await?;
+
+    bot.send_message(
+        message.chat.id,
+        format!("Content protection of {} updated", target.name),
+    )
+    .reply_parameters(reply_parameters)
+    .await?;
+
+    Ok(())
+}
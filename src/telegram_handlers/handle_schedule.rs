@@ -0,0 +1,68 @@
+use crate::{
+    config::Config, database::Database, scheduling::parse_local_datetime,
+    telegram_handlers::fetch_replied_post,
+};
+use chrono::Utc;
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+pub async fn handle_schedule(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+    datetime: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let scheduled_at = match datetime.trim() {
+        "off" | "cancel" => None,
+        value => match parse_local_datetime(value, &cfg.timezone) {
+            Some(scheduled_at) if scheduled_at > Utc::now() => Some(scheduled_at),
+            Some(_) => {
+                bot.send_message(message.chat.id, "Scheduled time is in the past")
+                    .reply_parameters(reply_parameters)
+                    .await?;
+                return Ok(());
+            }
+            None => {
+                bot.send_message(
+                    message.chat.id,
+                    "Usage: /schedule YYYY-MM-DD HH:MM (or /schedule off)",
+                )
+                .reply_parameters(reply_parameters)
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    if post.is_sent {
+        bot.send_message(message.chat.id, "Post was already sent")
+            .reply_parameters(reply_parameters)
+            .await?;
+        return Ok(());
+    }
+
+    db.set_post_schedule(post.id, scheduled_at.map(|v| v.naive_utc()))
+        .await?;
+
+    let text = match scheduled_at {
+        Some(scheduled_at) => format!(
+            "Post scheduled for {}",
+            scheduled_at
+                .with_timezone(&cfg.timezone)
+                .format("%Y-%m-%d %H:%M %Z")
+        ),
+        None => "Post returned to the queue".to_string(),
+    };
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
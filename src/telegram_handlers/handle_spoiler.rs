@@ -0,0 +1,45 @@
+use crate::{
+    database::{Database, MediaType},
+    telegram_handlers::fetch_replied_post,
+};
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+/// Toggles the spoiler blur of the replied post.
+pub async fn handle_spoiler(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let Some(post) = fetch_replied_post(&bot, &message, &db).await? else {
+        return Ok(());
+    };
+
+    let supported = matches!(
+        post.media_type,
+        MediaType::Photo | MediaType::Video | MediaType::Animation | MediaType::Album
+    );
+    if !supported {
+        bot.send_message(
+            message.chat.id,
+            "Only photos, videos and animations can be hidden behind a spoiler",
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    }
+
+    db.set_post_spoiler(post.id, !post.spoiler).await?;
+
+    let mut text = if post.spoiler {
+        "Spoiler removed".to_string()
+    } else {
+        "Post marked as spoiler".to_string()
+    };
+    if post.is_sent {
+        text.push_str(", takes effect when reposted");
+    }
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::{
+    config::Config,
+    database::{DUPLICATES_CAUGHT_SETTING, Database, Pause},
+    scheduling::{Cadence, PublicationSlots, localize},
+};
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+fn format_datetime(datetime: DateTime<Tz>) -> String {
+    datetime.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub async fn handle_stats(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    cfg: Config,
+) -> anyhow::Result<()> {
+    let now = Utc::now().with_timezone(&cfg.timezone);
+    let today = now.date_naive();
+    let week_start = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let start_of =
+        |date: NaiveDate| localize(&cfg.timezone, date.and_time(NaiveTime::MIN)).naive_utc();
+
+    let mut text = match db.pause().await? {
+        Some(Pause::Indefinitely) => "Publishing is paused\n\n".to_string(),
+        Some(Pause::Until(until)) => format!(
+            "Publishing is paused until {}\n\n",
+            format_datetime(until.with_timezone(&cfg.timezone))
+        ),
+        None => String::new(),
+    };
+    text.push_str("Queue:");
+    let by_media_type = db.unsent_counts_by_media_type().await?;
+    if by_media_type.is_empty() {
+        text.push_str(" empty");
+    }
+    for (media_type, count) in by_media_type {
+        text.push_str(&format!("\n{media_type}: {count}"));
+    }
+
+    text.push_str(&format!(
+        "\n\nSent today: {}, this week: {}",
+        db.sent_posts_count_since(start_of(today)).await?,
+        db.sent_posts_count_since(start_of(week_start)).await?,
+    ));
+    text.push_str(&format!(
+        "\nDeleted: {}, duplicates caught: {}, failed: {}",
+        db.deleted_posts_count().await?,
+        db.get_setting(DUPLICATES_CAUGHT_SETTING)
+            .await?
+            .unwrap_or_else(|| "0".to_string()),
+        db.fetch_failed_posts().await?.len(),
+    ));
+
+    text.push('\n');
+    for target in db.fetch_targets().await? {
+        let slots = target
+            .schedule
+            .as_deref()
+            .and_then(|v| v.parse::<PublicationSlots>().ok());
+        let cadence = Cadence {
+            interval: target.interval(),
+            slots: slots.as_ref(),
+            adaptive: cfg.adaptive.as_ref(),
+            windows: cfg.posting_windows.as_ref(),
+            group_threshold: target.group_threshold,
+            group_size: cfg.group_max_size,
+        };
+
+        let queued = db.unsent_posts_count(target.id).await?;
+        let last_sent = db
+            .last_sent_datetime(target.id)
+            .await?
+            .map(|v| v.and_utc().with_timezone(&cfg.timezone));
+        let next_send = cadence.next_send(queued, last_sent, now);
+
+        let forecast = match next_send.and_then(|at| cadence.run_out(queued, at)) {
+            _ if queued == 0 => "queue is empty".to_string(),
+            Some(at) => format!("runs out ~{}", format_datetime(at)),
+            None => "no forecast".to_string(),
+        };
+        let next_send = match next_send {
+            Some(at) => format_datetime(at),
+            None => "not scheduled".to_string(),
+        };
+        text.push_str(&format!(
+            "\n#{}: {queued} queued, {forecast}, next send {next_send}",
+            target.name
+        ));
+    }
+
+    if let Some(post) = db.fetch_next_scheduled_post().await?
+        && let Some(scheduled_at) = post.scheduled_at
+    {
+        text.push_str(&format!(
+            "\nNext pinned post: {}",
+            format_datetime(scheduled_at.and_utc().with_timezone(&cfg.timezone))
+        ));
+    }
+
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use crate::{
+    captions::caption_control,
+    database::{DEFAULT_TARGET_ID, Database},
+    scheduling::PublicationSlots,
+    utils::hashtags,
+};
+use std::{sync::Arc, time::Duration};
+use teloxide::{prelude::*, types::ReplyParameters};
+
+/// Shortest interval a target accepts, keeping a typo from flooding its chat.
+const MIN_TARGET_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Picks the target for a new submission: a `#name` caption tag wins over the
+/// sender chat's default, which wins over the default target.
+pub async fn resolve_target(db: &Database, message: &Message) -> i32 {
+    for tag in hashtags(caption_control(message)) {
+        match db.fetch_target_by_name(tag).await {
+            Ok(Some(target)) => return target.id,
+            Ok(None) => {}
+            Err(e) => log::error!("failed to fetch target: {e:?}"),
+        }
+    }
+
+    match db.fetch_sender_target(message.chat.id.0).await {
+        Ok(Some(target_id)) => target_id,
+        Ok(None) => DEFAULT_TARGET_ID,
+        Err(e) => {
+            log::error!("failed to fetch sender target: {e:?}");
+            DEFAULT_TARGET_ID
+        }
+    }
+}
+
+pub async fn handle_target(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    name: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let target = match db.fetch_target_by_name(name.trim()).await {
+        Ok(Some(target)) => target,
+        Ok(None) => {
+            bot.send_message(message.chat.id, "Target was not found, see /targets")
+                .reply_parameters(reply_parameters)
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            log::error!("failed to fetch target: {e:?}");
+            return Err(e);
+        }
+    };
+
+    let Some(reply_message) = message.reply_to_message() else {
+        db.set_sender_target(message.chat.id.0, target.id).await?;
+        bot.send_message(
+            message.chat.id,
+            format!("New posts from this chat will go to {}", target.name),
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    };
+
+    match db
+        .fetch_post_by_message_id(reply_message.chat.id.0, reply_message.id.0)
+        .await
+    {
+        Ok(Some(post)) => {
+            db.set_post_target(post.id, target.id).await?;
+            bot.send_message(message.chat.id, format!("Post moved to {}", target.name))
+                .reply_parameters(ReplyParameters::new(reply_message.id))
+                .await?;
+        }
+        Ok(None) => {
+            bot.send_message(message.chat.id, "Post was not found (already deleted?)")
+                .reply_parameters(ReplyParameters::new(reply_message.id))
+                .await?;
+        }
+        Err(e) => log::error!("failed to fetch post: {e:?}"),
+    }
+
+    Ok(())
+}
+
+pub async fn handle_targets(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let targets = db.fetch_targets().await?;
+
+    let mut text = String::from("Targets:");
+    for target in targets {
+        let timing = match (&target.schedule, target.interval()) {
+            (Some(schedule), _) => format!("schedule {schedule}"),
+            (None, Some(interval)) => format!("every {}", humantime::format_duration(interval)),
+            (None, None) => "not scheduled".to_string(),
+        };
+        text.push_str(&format!(
+            "\n#{} → {}, {timing}, group threshold {}",
+            target.name, target.chat_id, target.group_threshold
+        ));
+    }
+
+    bot.send_message(message.chat.id, text)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+/// `/addtarget <name> <chat_id> <group_threshold> <interval | schedule>`,
+/// updating the target if one with the same name already exists.
+pub async fn handle_add_target(
+    bot: Bot,
+    message: Message,
+    db: Arc<Database>,
+    args: String,
+) -> anyhow::Result<()> {
+    let reply_parameters = ReplyParameters::new(message.id);
+
+    let parts: Vec<&str> = args.trim().splitn(4, ' ').collect();
+    let [name, chat_id, group_threshold, timing] = parts[..] else {
+        bot.send_message(
+            message.chat.id,
+            "Usage: /addtarget <name> <chat_id> <group_threshold> <interval | schedule>",
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    };
+
+    let (Ok(chat_id), Ok(group_threshold)) = (chat_id.parse(), group_threshold.parse()) else {
+        bot.send_message(
+            message.chat.id,
+            "Chat id and group threshold must be numbers",
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    };
+
+    let (interval, schedule) = match humantime::parse_duration(timing) {
+        Ok(interval) => (Some(interval), None),
+        Err(_) => match timing.parse::<PublicationSlots>() {
+            Ok(slots) => (None, Some(slots.to_string())),
+            Err(e) => {
+                bot.send_message(
+                    message.chat.id,
+                    format!("Invalid interval or schedule: {e}"),
+                )
+                .reply_parameters(reply_parameters)
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    if interval.is_some_and(|interval| interval < MIN_TARGET_INTERVAL) {
+        bot.send_message(
+            message.chat.id,
+            format!(
+                "Interval must be at least {}",
+                humantime::format_duration(MIN_TARGET_INTERVAL)
+            ),
+        )
+        .reply_parameters(reply_parameters)
+        .await?;
+        return Ok(());
+    }
+
+    let target = db
+        .save_target(name, chat_id, interval, schedule, group_threshold)
+        .await?;
+
+    bot.send_message(message.chat.id, format!("Target {} saved", target.name))
+        .reply_parameters(reply_parameters)
+        .await?;
+
+    Ok(())
+}
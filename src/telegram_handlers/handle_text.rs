@@ -0,0 +1,53 @@
+use crate::{
+    captions::split_caption,
+    database::{Database, MediaType},
+    telegram_handlers::{new_post, refuse_oversized},
+};
+use std::sync::Arc;
+use teloxide::{
+    prelude::*,
+    types::{ReactionType, ReplyParameters},
+};
+
+/// Text messages explicitly marked as posts with a separator line, so that
+/// chatting with the bot does not queue anything. Replies and unknown
+/// commands are left out, as they are meant for the bot rather than the
+/// channel.
+pub fn text_post(message: Message) -> bool {
+    message
+        .text()
+        .is_some_and(|text| !text.starts_with('/') && split_caption(text).is_some())
+        && message.reply_to_message().is_none()
+}
+
+pub async fn handle_text(bot: Bot, message: Message, db: Arc<Database>) -> anyhow::Result<()> {
+    let new_post = new_post(&db, &message, MediaType::Text, String::new()).await;
+
+    if new_post.caption.is_none() {
+        bot.send_message(message.chat.id, "Text post is empty")
+            .reply_parameters(ReplyParameters::new(message.id))
+            .await?;
+        return Ok(());
+    }
+    if refuse_oversized(&bot, &message, &new_post).await? {
+        return Ok(());
+    }
+
+    let create_post_future = db.create_post(new_post, message.chat.id.0, message.id.0);
+    match create_post_future.await {
+        Ok(_) => {
+            log::info!("Post saved");
+
+            bot.set_message_reaction(message.chat.id, message.id)
+                .reaction(vec![ReactionType::Emoji {
+                    emoji: "👍".to_string(),
+                }])
+                .await?;
+        }
+        Err(e) => {
+            log::error!("Error saving post: {e:?}");
+        }
+    }
+
+    Ok(())
+}
@@ -1,9 +1,16 @@
+use crate::captions::CAPTION_SEPARATOR;
 use teloxide::{Bot, prelude::*, types::ReplyParameters};
 
 pub async fn handle_unknown(bot: Bot, message: Message) -> anyhow::Result<()> {
     let reply_parameters = ReplyParameters::new(message.id);
 
-    bot.send_message(message.chat.id, "Unknown message type")
+    let reply = match message.text() {
+        Some(text) if !text.starts_with('/') && message.reply_to_message().is_none() => format!(
+            "To queue a text post, add a {CAPTION_SEPARATOR} line below it, optionally followed by keywords"
+        ),
+        _ => "Unknown message type".to_string(),
+    };
+    bot.send_message(message.chat.id, reply)
         .reply_parameters(reply_parameters)
         .await?;
 
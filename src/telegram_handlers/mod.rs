@@ -1,11 +1,53 @@
+mod handle_album;
 mod handle_animation;
+mod handle_callback;
+mod handle_caption;
 mod handle_del;
+mod handle_document;
+mod handle_failed;
+mod handle_forward;
+mod handle_media;
+mod handle_order;
+mod handle_pause;
 mod handle_photo;
+mod handle_priority;
+mod handle_published;
+mod handle_publishing;
+mod handle_schedule;
+mod handle_spoiler;
+mod handle_stats;
+mod handle_target;
+mod handle_text;
 mod handle_unknown;
 mod handle_video;
+mod replied_post;
+mod submission;
 
+pub use handle_album::{Albums, album_item, handle_album};
 pub use handle_animation::handle_animation;
+pub use handle_callback::{handle_callback, post_keyboard};
+pub use handle_caption::handle_caption;
 pub use handle_del::handle_del;
+pub use handle_document::{handle_document, image_document};
+pub use handle_failed::{handle_failed, handle_requeue};
+pub use handle_forward::{handle_published_forward, published_post};
+pub use handle_media::{handle_audio, handle_sticker, handle_video_note, handle_voice};
+pub use handle_order::handle_order;
+pub use handle_pause::{handle_pause, handle_resume};
 pub use handle_photo::handle_photo;
+pub use handle_priority::{handle_next, handle_priority, handle_send_now};
+pub use handle_published::{
+    handle_channel_command, handle_recaption, handle_repost, handle_unpost,
+};
+pub use handle_publishing::{handle_protect, handle_silent};
+pub use handle_schedule::handle_schedule;
+pub use handle_spoiler::handle_spoiler;
+pub use handle_stats::handle_stats;
+pub use handle_target::{handle_add_target, handle_target, handle_targets, resolve_target};
+pub use handle_text::{handle_text, text_post};
 pub use handle_unknown::handle_unknown;
 pub use handle_video::handle_video;
+pub use replied_post::fetch_replied_post;
+pub use submission::{
+    clip_fingerprint, download, new_post, photo_hash, refuse_oversized, report_duplicate,
+};
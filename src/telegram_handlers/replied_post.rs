@@ -0,0 +1,30 @@
+use crate::database::{Database, Post};
+use teloxide::{prelude::*, types::ReplyParameters};
+
+/// Looks up the post referenced by the message a command replies to,
+/// answering the command itself when there is nothing to act on.
+pub async fn fetch_replied_post(
+    bot: &Bot,
+    message: &Message,
+    db: &Database,
+) -> anyhow::Result<Option<Post>> {
+    let Some(reply_message) = message.reply_to_message() else {
+        bot.send_message(message.chat.id, "Reply required")
+            .reply_parameters(ReplyParameters::new(message.id))
+            .await?;
+        return Ok(None);
+    };
+
+    match db
+        .fetch_post_by_message_id(reply_message.chat.id.0, reply_message.id.0)
+        .await?
+    {
+        Some(post) => Ok(Some(post)),
+        None => {
+            bot.send_message(message.chat.id, "Post was not found (already deleted?)")
+                .reply_parameters(ReplyParameters::new(reply_message.id))
+                .await?;
+            Ok(None)
+        }
+    }
+}
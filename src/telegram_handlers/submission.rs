@@ -0,0 +1,263 @@
+use crate::{
+    captions::{
+        caption_control, caption_limit, clip_entities, split_caption, split_text, strip_keywords,
+    },
+    database::{DUPLICATES_CAUGHT_SETTING, Database, MediaType, NewPost, Post},
+    telegram_handlers::resolve_target,
+    utils::{download_file, hashtags, image_hash, telegram_len},
+};
+use teloxide::{
+    prelude::*,
+    types::{InputFile, MessageEntityKind, MessageOrigin, ReplyParameters},
+};
+
+/// Builds the post for a submitted message, capturing the caption part (or
+/// text body) meant for the channel and the details caption templates can
+/// refer to: contributor, hashtags and source link.
+pub async fn new_post(
+    db: &Database,
+    message: &Message,
+    media_type: MediaType,
+    file_id: String,
+) -> NewPost {
+    let target_id = resolve_target(db, message).await;
+
+    let contributor = match (&message.sender_chat, &message.from) {
+        (Some(chat), _) => chat.title().map(str::to_string),
+        (None, Some(user)) => Some(user.full_name()),
+        (None, None) => None,
+    };
+
+    let mut tags = Vec::new();
+    let mut target_tags = Vec::new();
+    for tag in hashtags(caption_control(message)) {
+        // Tags naming a target only route the post.
+        match db.fetch_target_by_name(tag).await {
+            Ok(Some(_)) => target_tags.push(format!("#{tag}")),
+            Ok(None) => tags.push(format!("#{tag}")),
+            Err(e) => log::error!("failed to fetch target: {e:?}"),
+        }
+    }
+
+    let (caption, entities) = match (message.text(), message.caption()) {
+        (Some(text), _) => {
+            let body = split_text(text).0;
+            (
+                body,
+                clip_entities(body, message.entities().unwrap_or_default()),
+            )
+        }
+        (None, Some(caption)) => {
+            let entities = message.caption_entities().unwrap_or_default();
+            match split_caption(caption) {
+                Some((published, _)) => (published, clip_entities(published, entities)),
+                None => strip_keywords(caption, entities, |word| {
+                    word == "force" || target_tags.iter().any(|tag| tag == word)
+                }),
+            }
+        }
+        (None, None) => ("", vec![]),
+    };
+    let caption = (!caption.is_empty()).then(|| caption.to_string());
+    let caption_entities = Some(entities)
+        .filter(|entities| !entities.is_empty())
+        .and_then(|entities| serde_json::to_string(&entities).ok());
+
+    NewPost {
+        target_id,
+        contributor,
+        source_url: source_url(message),
+        tags: (!tags.is_empty()).then(|| tags.join(" ")),
+        caption,
+        caption_entities,
+        spoiler: message.has_media_spoiler(),
+        ..NewPost::new(media_type, file_id)
+    }
+}
+
+/// Refuses a submission whose text or caption is longer than Telegram allows
+/// for its media type, replying why. Returns whether it was refused.
+pub async fn refuse_oversized(
+    bot: &Bot,
+    message: &Message,
+    new_post: &NewPost,
+) -> anyhow::Result<bool> {
+    let limit = caption_limit(&new_post.media_type);
+    let length = new_post.caption.as_deref().map_or(0, telegram_len);
+    if length <= limit {
+        return Ok(false);
+    }
+
+    log::warn!("Caption of {length} characters exceeds the limit of {limit}");
+    bot.send_message(
+        message.chat.id,
+        format!("Text is too long: {length} characters, the limit is {limit}"),
+    )
+    .reply_parameters(ReplyParameters::new(message.id))
+    .await?;
+    Ok(true)
+}
+
+/// The first link in the caption or text, or the original post when forwarded from a
+/// public channel.
+fn source_url(message: &Message) -> Option<String> {
+    let from_caption = message
+        .parse_caption_entities()
+        .or_else(|| message.parse_entities())
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|entity| match entity.kind() {
+            MessageEntityKind::Url => Some(entity.text().to_string()),
+            MessageEntityKind::TextLink { url } => Some(url.to_string()),
+            _ => None,
+        });
+
+    from_caption.or_else(|| match message.forward_origin() {
+        Some(MessageOrigin::Channel {
+            chat, message_id, ..
+        }) => Message::url_of(chat.id, chat.username(), *message_id).map(|url| url.to_string()),
+        _ => None,
+    })
+}
+
+/// Downloads a submitted file.
+pub async fn download(bot: &Bot, file_id: &str) -> anyhow::Result<Vec<u8>> {
+    let file = match bot.get_file(file_id).await {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("Error fetching file: {e:?}");
+            return Err(e.into());
+        }
+    };
+
+    let download_resp = match download_file(&file, bot.token()).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            log::error!("Error downloading file: {e:?}");
+            return Err(e.into());
+        }
+    };
+
+    Ok(download_resp.bytes().await?.to_vec())
+}
+
+/// Downloads a submitted photo and computes its perceptual hash.
+pub async fn photo_hash(bot: &Bot, file_id: &str) -> anyhow::Result<String> {
+    image_hash(&download(bot, file_id).await?)
+}
+
+/// Fingerprints a submitted video or animation by the perceptual hash of the
+/// thumbnail Telegram generated for it, along with its duration and
+/// dimensions, as thumbnails alone often look alike across clips. `None`
+/// without a thumbnail or when it cannot be downloaded.
+pub async fn clip_fingerprint(bot: &Bot, message: &Message) -> Option<String> {
+    let (thumbnail, duration, width, height) = match (message.video(), message.animation()) {
+        (Some(video), _) => (&video.thumbnail, video.duration, video.width, video.height),
+        (None, Some(animation)) => (
+            &animation.thumbnail,
+            animation.duration,
+            animation.width,
+            animation.height,
+        ),
+        (None, None) => return None,
+    };
+
+    match photo_hash(bot, &thumbnail.as_ref()?.file.id).await {
+        Ok(hash) => Some(format!("{hash}:{}:{width}x{height}", duration.seconds())),
+        Err(e) => {
+            log::error!("Unable to hash thumbnail: {e:?}");
+            None
+        }
+    }
+}
+
+/// Replies to a submission that duplicates `post`, linking both the
+/// submission and the reply to the existing post. `submitted_photo` is shown
+/// when the existing post cannot be, such as an album.
+pub async fn report_duplicate(
+    bot: &Bot,
+    db: &Database,
+    message: &Message,
+    submitted_photo: Option<&str>,
+    post: &Post,
+) -> anyhow::Result<()> {
+    if let Err(e) = db.increment_setting(DUPLICATES_CAUGHT_SETTING).await {
+        log::error!("Error counting duplicate: {e:?}");
+    }
+
+    match db
+        .add_message_id_for_post(post.id, message.chat.id.0, message.id.0)
+        .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("Error saving post message id: {e:?}");
+        }
+    }
+
+    let text = format!(
+        "Duplicate from {}",
+        post.created_datetime.and_utc().to_rfc3339()
+    );
+    let reply_parameters = ReplyParameters::new(message.id);
+    let chat_id = message.chat.id;
+    let notification = match (&post.media_type, submitted_photo) {
+        (MediaType::Photo, _) => {
+            bot.send_photo(chat_id, InputFile::file_id(&post.file_id))
+                .caption(text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+        (MediaType::Document, _) => {
+            bot.send_document(chat_id, InputFile::file_id(&post.file_id))
+                .caption(text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+        (MediaType::Video, _) => {
+            bot.send_video(chat_id, InputFile::file_id(&post.file_id))
+                .caption(text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+        (MediaType::Animation, _) => {
+            bot.send_animation(chat_id, InputFile::file_id(&post.file_id))
+                .caption(text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+        (_, Some(file_id)) => {
+            bot.send_photo(chat_id, InputFile::file_id(file_id))
+                .caption(text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+        (_, None) => {
+            bot.send_message(chat_id, text)
+                .reply_parameters(reply_parameters)
+                .await
+        }
+    };
+
+    match notification {
+        Ok(msg) => {
+            log::info!("Sent duplicate notification");
+
+            match db
+                .add_message_id_for_post(post.id, msg.chat.id.0, msg.id.0)
+                .await
+            {
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Error saving post message id: {e:?}");
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("Error sending duplicate notification: {e:?}");
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
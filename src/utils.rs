@@ -1,5 +1,7 @@
+use image::{ImageFormat, imageops::FilterType};
 use imghash::{ImageHasher, perceptual::PerceptualHasher};
 use reqwest::Response;
+use std::io::Cursor;
 use teloxide::types::File;
 
 pub async fn download_file(file: &File, token: &str) -> reqwest::Result<Response> {
@@ -15,3 +17,38 @@ pub fn image_hash(image_bytes: &[u8]) -> anyhow::Result<String> {
     let hasher = PerceptualHasher::default();
     Ok(hasher.hash_from_img(&img).encode())
 }
+
+/// Largest side of a photo converted from a document; Telegram downscales
+/// bigger photos anyway.
+const PHOTO_MAX_SIDE: u32 = 2560;
+
+/// Re-encodes an image as a JPEG Telegram accepts as a photo, downscaling it
+/// to fit [`PHOTO_MAX_SIDE`].
+pub fn normalize_photo(image_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut img = image::load_from_memory(image_bytes)?;
+    if img.width() > PHOTO_MAX_SIDE || img.height() > PHOTO_MAX_SIDE {
+        img = img.resize(PHOTO_MAX_SIDE, PHOTO_MAX_SIDE, FilterType::Lanczos3);
+    }
+
+    let mut photo = Vec::new();
+    img.to_rgb8()
+        .write_to(&mut Cursor::new(&mut photo), ImageFormat::Jpeg)?;
+    Ok(photo)
+}
+
+/// Longest text message Telegram accepts, in UTF-16 code units.
+pub const MESSAGE_MAX_LENGTH: usize = 4096;
+
+/// Longest media caption Telegram accepts, in UTF-16 code units.
+pub const CAPTION_MAX_LENGTH: usize = 1024;
+
+/// Length of `text` the way Telegram counts it.
+pub fn telegram_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+pub fn hashtags(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .filter(|tag| !tag.is_empty())
+}
@@ -10,10 +10,15 @@ use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::{io::Cursor, sync::Arc};
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 use tower::ServiceBuilder;
 use tower_http::cors::{Any, CorsLayer};
 
-pub async fn run_server(cfg: Config, db: Arc<Database>) -> anyhow::Result<()> {
+pub async fn run_server(
+    cfg: Config,
+    db: Arc<Database>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
     let cors = CorsLayer::new()
         .allow_methods([Method::POST])
         .allow_origin(Any);
@@ -24,7 +29,11 @@ pub async fn run_server(cfg: Config, db: Arc<Database>) -> anyhow::Result<()> {
         .with_state(db);
 
     let listener = TcpListener::bind(format!("0.0.0.0:{}", cfg.api_port.unwrap())).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+
+    log::info!("API server stopped");
 
     Ok(())
 }
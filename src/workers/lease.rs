@@ -0,0 +1,90 @@
+use crate::{config::Config, database::Database};
+use std::{future::Future, sync::Arc};
+use tokio_util::sync::CancellationToken;
+
+/// Runs `work` whenever this instance holds the lease `name`, until
+/// `shutdown` is requested. `work` gets a token that is cancelled once the
+/// lease is lost, and is started again after the lease is won back. While
+/// another instance holds the lease this one stands by.
+pub async fn run_with_lease<F, Fut>(
+    name: &'static str,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+    mut work: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(CancellationToken) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let mut standby_logged = false;
+    while !shutdown.is_cancelled() {
+        match db
+            .acquire_lease(name, &cfg.instance_id, cfg.lease_ttl)
+            .await
+        {
+            Ok(lease) if lease.holder == cfg.instance_id => {}
+            Ok(lease) => {
+                if !standby_logged {
+                    log::info!(
+                        "{name} lease is held by {} until {}, standing by",
+                        lease.holder,
+                        lease.expires_at
+                    );
+                    standby_logged = true;
+                }
+                shutdown
+                    .run_until_cancelled(tokio::time::sleep(cfg.lease_ttl / 3))
+                    .await;
+                continue;
+            }
+            Err(e) => {
+                log::error!("Error acquiring {name} lease: {e:?}");
+                shutdown
+                    .run_until_cancelled(tokio::time::sleep(cfg.lease_ttl / 3))
+                    .await;
+                continue;
+            }
+        }
+
+        log::info!("acquired {name} lease as {}", cfg.instance_id);
+        standby_logged = false;
+        let lease = shutdown.child_token();
+        let heartbeat = tokio::spawn(hold_lease(name, db.clone(), cfg.clone(), lease.clone()));
+        let result = work(lease.clone()).await;
+        lease.cancel();
+        heartbeat.await?;
+
+        if let Err(e) = db.release_lease(name, &cfg.instance_id).await {
+            log::error!("Error releasing {name} lease: {e:?}");
+        }
+        result?;
+    }
+
+    Ok(())
+}
+
+/// Renews the lease `name` until `lease` is cancelled, cancelling it when
+/// the lease is lost so that the work holding it stops.
+async fn hold_lease(name: &'static str, db: Arc<Database>, cfg: Config, lease: CancellationToken) {
+    while lease
+        .run_until_cancelled(tokio::time::sleep(cfg.lease_ttl / 3))
+        .await
+        .is_some()
+    {
+        match db
+            .acquire_lease(name, &cfg.instance_id, cfg.lease_ttl)
+            .await
+        {
+            Ok(current) if current.holder == cfg.instance_id => {}
+            Ok(current) => {
+                log::warn!("{name} lease was taken over by {}", current.holder);
+                lease.cancel();
+            }
+            Err(e) => {
+                log::error!("Error renewing {name} lease: {e:?}");
+                lease.cancel();
+            }
+        }
+    }
+}
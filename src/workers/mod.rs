@@ -1,9 +1,10 @@
 mod api;
+mod lease;
 mod sender;
 mod telegram_bot;
 mod uploader;
 
 pub use api::run_server;
-pub use sender::run_sender;
-pub use telegram_bot::run_bot;
+pub use sender::{publish_now, run_sender};
+pub use telegram_bot::{Commands, run_bot};
 pub use uploader::run_uploader;
@@ -1,94 +1,794 @@
 use crate::{
-    config::Config,
-    database::{Database, MediaType, Post},
+    captions::render_caption,
+    config::{Config, SenderMode},
+    database::{Database, MediaType, Pause, Post, PostItem, SENDER_LEASE, Target},
+    scheduling::{PublicationSlots, SilentHours, instant_at},
+    workers::lease::run_with_lease,
 };
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
 use std::ops::Add;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use teloxide::{
+    RequestError,
     prelude::*,
-    types::{InputFile, InputMedia, InputMediaPhoto},
+    types::{InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, ParseMode},
 };
-use tokio::time::Instant;
+use tokio::{
+    task::{Id, JoinSet},
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
 
-pub async fn run_sender(bot: Bot, db: Arc<Database>, cfg: Config) {
-    loop {
-        log::info!("sender iteration");
+const RETRY_DELAY: Duration = Duration::from_secs(60);
 
-        let unsent_posts_count = db.unsent_posts_count().await.unwrap();
+/// Runs the senders until `shutdown` is requested. A live sender only
+/// publishes while holding the sender lease, standing by while another
+/// instance sharing the database holds it.
+pub async fn run_sender(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    if cfg.sender_mode != SenderMode::Live {
+        log::warn!(
+            "sender is running in {} mode, posts will not be marked as sent",
+            cfg.sender_mode
+        );
+        db.simulate_sender_settings();
+        return run_senders(bot, db, cfg, shutdown).await;
+    }
+
+    run_with_lease(SENDER_LEASE, db.clone(), cfg.clone(), shutdown, |lease| {
+        run_senders(bot.clone(), db.clone(), cfg.clone(), lease)
+    })
+    .await
+}
 
-        match db.fetch_unsent_post().await {
-            Ok(Some(post)) => {
-                if post.is_photo()
-                    && cfg.group_threshold > 0
-                    && unsent_posts_count > cfg.group_threshold
+/// Runs a sender per target plus the sender of pinned posts until `shutdown`
+/// is requested. Senders only stop between sends, so a post is never left
+/// published but unmarked. A sender that panics is restarted after a delay.
+async fn run_senders(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    // Target senders are keyed by target id, the scheduled sender by `None`.
+    let mut senders = JoinSet::new();
+    let mut running: HashMap<Id, Option<i32>> = HashMap::new();
+
+    let spawn = |senders: &mut JoinSet<()>, target_id: Option<i32>, delay: Duration| {
+        let (bot, db, cfg, shutdown) = (bot.clone(), db.clone(), cfg.clone(), shutdown.clone());
+        senders
+            .spawn(async move {
+                if shutdown
+                    .run_until_cancelled(tokio::time::sleep(delay))
+                    .await
+                    .is_none()
                 {
-                    match db.fetch_ten_unsent_photo_posts().await {
-                        Ok(posts) => {
-                            match send_group_photo_post(posts.clone(), bot.clone(), cfg.clone())
-                                .await
-                            {
-                                Ok(_) => {
-                                    match db.mark_sent_posts(posts.iter().map(|p| p.id)).await {
-                                        Ok(_) => log::info!("Marked as sent"),
-                                        Err(e) => {
-                                            log::error!("Unable to mark posts as sent: {e:?}")
-                                        }
-                                    }
-                                }
-                                Err(e) => log::error!("Error sending multiple posts: {e:?}"),
-                            }
+                    return;
+                }
+                match target_id {
+                    Some(target_id) => run_target_sender(bot, db, cfg, target_id, shutdown).await,
+                    None => run_scheduled_sender(bot, db, cfg, shutdown).await,
+                }
+            })
+            .id()
+    };
+
+    running.insert(spawn(&mut senders, None, Duration::ZERO), None);
+    let mut refresh = true;
+    while !shutdown.is_cancelled() {
+        if refresh {
+            match db.fetch_targets().await {
+                Ok(targets) => {
+                    for target in targets {
+                        if running.values().any(|id| *id == Some(target.id)) {
+                            continue;
                         }
-                        Err(e) => log::error!("Error fetching multiple posts: {e:?}"),
+                        log::info!("starting sender for target {}", target.name);
+                        let task = spawn(&mut senders, Some(target.id), Duration::ZERO);
+                        running.insert(task, Some(target.id));
                     }
-                } else {
-                    match send_post(post.clone(), bot.clone(), cfg.clone()).await {
-                        Ok(_) => match db.mark_sent_posts(vec![post.id]).await {
-                            Ok(_) => log::info!("Marked as sent"),
-                            Err(e) => log::error!("Unable to mark post as sent: {e:?}"),
-                        },
-                        Err(e) => log::error!("Error sending post: {e:?}"),
+                }
+                Err(e) => log::error!("Error fetching targets: {e:?}"),
+            }
+        }
+
+        tokio::select! {
+            _ = db.targets_changed.notified() => refresh = true,
+            _ = shutdown.cancelled() => {}
+            Some(result) = senders.join_next_with_id() => {
+                refresh = false;
+                match result {
+                    Ok((task, ())) => {
+                        running.remove(&task);
+                    }
+                    Err(e) => {
+                        let target_id = running.remove(&e.id()).flatten();
+                        let sender = target_id
+                            .map_or("scheduled posts".to_string(), |id| format!("target {id}"));
+                        log::error!(
+                            "sender for {sender} panicked, restarting in {}s: {e:?}",
+                            RETRY_DELAY.as_secs()
+                        );
+                        let task = spawn(&mut senders, target_id, RETRY_DELAY);
+                        running.insert(task, target_id);
+                    }
+                }
+            }
+        }
+    }
+
+    log::info!("waiting for senders to finish...");
+    let mut panicked = 0;
+    while let Some(result) = senders.join_next().await {
+        if let Err(e) = result {
+            log::error!("sender panicked: {e:?}");
+            panicked += 1;
+        }
+    }
+
+    match panicked {
+        0 => Ok(()),
+        count => anyhow::bail!("{count} sender task(s) panicked"),
+    }
+}
+
+async fn run_target_sender(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    target_id: i32,
+    shutdown: CancellationToken,
+) {
+    while !shutdown.is_cancelled() {
+        let target = match db.fetch_target(target_id).await {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                log::warn!("target {target_id} no longer exists, stopping its sender");
+                return;
+            }
+            Err(e) => {
+                log::error!("Error fetching target {target_id}: {e:?}");
+                shutdown
+                    .run_until_cancelled(tokio::time::sleep(RETRY_DELAY))
+                    .await;
+                continue;
+            }
+        };
+
+        let slots = match target.schedule.as_deref().map(PublicationSlots::from_str) {
+            Some(Ok(slots)) => Some(slots),
+            Some(Err(e)) => {
+                log::error!("invalid schedule for target {}: {e:?}", target.name);
+                return;
+            }
+            None => None,
+        };
+        if slots.is_none() && target.interval().is_none() {
+            log::error!("target {} has neither interval nor schedule", target.name);
+            return;
+        }
+        let interval = target.interval().unwrap_or(RETRY_DELAY);
+
+        let slot = match &slots {
+            Some(slots) => match wait_for_slot(slots, &db, &cfg, &target, &shutdown).await {
+                Some(slot) => Some(slot),
+                None if shutdown.is_cancelled() => return,
+                None => {
+                    log::error!("schedule of target {} has no upcoming slots", target.name);
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        if is_paused(&db).await {
+            match slot {
+                Some(slot) => {
+                    log::info!("publishing is paused, skipping slot {slot}");
+                    record_fired_slot(&db, &target, slot).await;
+                }
+                None => wait_until_resumed(&db, &shutdown).await,
+            }
+            continue;
+        }
+
+        let now = Utc::now().with_timezone(&cfg.timezone);
+        let window_end = match &cfg.posting_windows {
+            Some(windows) => match windows.current_window_end(now) {
+                Some(end) => Some(end),
+                None => {
+                    if let Some(slot) = slot {
+                        log::info!("slot {slot} is outside of posting windows, skipping");
+                        record_fired_slot(&db, &target, slot).await;
+                        continue;
+                    }
+                    match windows.next_window_start(now) {
+                        Some(start) => {
+                            log::info!("outside of posting windows, sleeping until {start}");
+                            shutdown
+                                .run_until_cancelled(tokio::time::sleep_until(instant_at(&start)))
+                                .await;
+                        }
+                        None => {
+                            log::warn!("no posting windows configured for the upcoming week");
+                            shutdown
+                                .run_until_cancelled(tokio::time::sleep(interval))
+                                .await;
+                        }
+                    }
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        log::info!("sender iteration for target {}", target.name);
+
+        let queued = match db.unsent_posts_count(target.id).await {
+            Ok(count) => count,
+            Err(e) => {
+                log::error!("Error counting unsent posts: {e:?}");
+                0
+            }
+        };
+        let (mut interval, group_size) = match (&cfg.adaptive, slot) {
+            (Some(pacing), None) => pacing.plan(queued, cfg.group_max_size),
+            _ if target.group_threshold > 0 && queued > target.group_threshold => {
+                (interval, cfg.group_max_size)
+            }
+            _ => (interval, 1),
+        };
+
+        send_next(&bot, &db, &cfg, &target, group_size, &shutdown).await;
+
+        if let Some(slot) = slot {
+            record_fired_slot(&db, &target, slot).await;
+            continue;
+        }
+
+        if let Some(end) = window_end.filter(|_| cfg.spread_interval) {
+            match db.unsent_posts_count(target.id).await {
+                Ok(count) if count > 0 => {
+                    let remaining = end
+                        .signed_duration_since(Utc::now())
+                        .to_std()
+                        .unwrap_or_default();
+                    interval = interval.max(remaining / count as u32);
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("Error counting unsent posts: {e:?}"),
+            }
+        }
+
+        shutdown
+            .run_until_cancelled(tokio::time::sleep_until(Instant::now().add(interval)))
+            .await;
+    }
+}
+
+/// Publishes posts that have an exact `scheduled_at`, independently of the
+/// regular per-target schedules.
+async fn run_scheduled_sender(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) {
+    while !shutdown.is_cancelled() {
+        let post = match db.fetch_next_scheduled_post().await {
+            Ok(Some(post)) => post,
+            Ok(None) => {
+                shutdown
+                    .run_until_cancelled(db.schedule_changed.notified())
+                    .await;
+                continue;
+            }
+            Err(e) => {
+                log::error!("Error fetching scheduled post: {e:?}");
+                shutdown
+                    .run_until_cancelled(tokio::time::sleep(RETRY_DELAY))
+                    .await;
+                continue;
+            }
+        };
+
+        let scheduled_at = post.scheduled_at.unwrap().and_utc();
+        let due = post
+            .next_attempt_at
+            .map_or(scheduled_at, |v| v.and_utc().max(scheduled_at));
+        if due > Utc::now() {
+            tokio::select! {
+                _ = tokio::time::sleep_until(instant_at(&due)) => {}
+                _ = db.schedule_changed.notified() => continue,
+                _ = shutdown.cancelled() => continue,
+            }
+        }
+
+        if is_paused(&db).await {
+            wait_until_resumed(&db, &shutdown).await;
+            continue;
+        }
+
+        log::info!("sending post scheduled at {scheduled_at}");
+        if let Err(e) = publish_now(bot.clone(), &db, &cfg, post, &shutdown).await {
+            log::error!("Error sending scheduled post: {e:?}");
+            // Not every failure holds the post back, so avoid retrying at once.
+            shutdown
+                .run_until_cancelled(tokio::time::sleep(RETRY_DELAY))
+                .await;
+        }
+    }
+}
+
+async fn is_paused(db: &Database) -> bool {
+    match db.pause().await {
+        Ok(pause) => pause.is_some(),
+        Err(e) => {
+            log::error!("Error fetching pause state: {e:?}");
+            false
+        }
+    }
+}
+
+/// Sleeps until publishing is resumed. Pause changes wake it up early, and it
+/// re-checks at least every [`RETRY_DELAY`] in case a change slipped in
+/// before it started waiting.
+async fn wait_until_resumed(db: &Database, shutdown: &CancellationToken) {
+    log::info!("publishing is paused");
+    loop {
+        let wait = match db.pause().await {
+            Ok(Some(Pause::Until(until))) => until
+                .signed_duration_since(Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .min(RETRY_DELAY),
+            Ok(Some(Pause::Indefinitely)) => RETRY_DELAY,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("Error fetching pause state: {e:?}");
+                RETRY_DELAY
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = db.pause_changed.notified() => {}
+            _ = shutdown.cancelled() => return,
+        }
+    }
+}
+
+fn last_fired_slot_key(target: &Target) -> String {
+    format!("last_fired_slot:{}", target.id)
+}
+
+/// Sleeps until the slot following the last fired one. Slots missed while
+/// the process was down are collapsed into a single immediate send. Returns
+/// `None` when there are no more slots or on shutdown.
+async fn wait_for_slot(
+    slots: &PublicationSlots,
+    db: &Database,
+    cfg: &Config,
+    target: &Target,
+    shutdown: &CancellationToken,
+) -> Option<DateTime<Tz>> {
+    let now = Utc::now().with_timezone(&cfg.timezone);
+    let last_fired = match db.get_sender_setting(&last_fired_slot_key(target)).await {
+        Ok(value) => value
+            .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+            .map(|v| v.with_timezone(&cfg.timezone)),
+        Err(e) => {
+            log::error!("Error fetching last fired slot: {e:?}");
+            None
+        }
+    };
+
+    let slot = slots.next_after(last_fired.unwrap_or(now))?;
+    if slot > now {
+        log::info!("next slot for target {} at {slot}", target.name);
+        shutdown
+            .run_until_cancelled(tokio::time::sleep_until(instant_at(&slot)))
+            .await?;
+        Some(slot)
+    } else {
+        log::info!("catching up on missed slot {slot}");
+        Some(now)
+    }
+}
+
+async fn record_fired_slot(db: &Database, target: &Target, slot: DateTime<Tz>) {
+    if let Err(e) = db
+        .set_sender_setting(&last_fired_slot_key(target), slot.to_rfc3339())
+        .await
+    {
+        log::error!("Unable to record fired slot: {e:?}");
+    }
+}
+
+/// Sends the next post, or an album of up to `group_size` posts when the
+/// picked post can be grouped.
+async fn send_next(
+    bot: &Bot,
+    db: &Database,
+    cfg: &Config,
+    target: &Target,
+    group_size: i64,
+    shutdown: &CancellationToken,
+) {
+    let order = match db.queue_order(cfg.queue_order).await {
+        Ok(order) => order,
+        Err(e) => {
+            log::error!("Error fetching queue order: {e:?}");
+            cfg.queue_order
+        }
+    };
+
+    match db.fetch_unsent_post(target.id, order).await {
+        Ok(Some(post)) => {
+            let group = if post.is_groupable() && group_size >= cfg.group_min_size {
+                match db
+                    .fetch_unsent_group_posts(target.id, order, group_size)
+                    .await
+                {
+                    Ok(posts) if posts.len() as i64 >= cfg.group_min_size => Some(posts),
+                    Ok(_) => None,
+                    Err(e) => {
+                        log::error!("Error fetching multiple posts: {e:?}");
+                        return;
                     }
                 }
+            } else {
+                None
+            };
+
+            let posts = group.unwrap_or_else(|| vec![post]);
+            if let Err(e) = deliver(bot, db, cfg, target, posts, shutdown).await {
+                log::error!("Error sending post: {e:?}");
             }
-            Ok(None) => log::info!("Nothing to send"),
-            Err(e) => log::error!("Error fetching unsent posts: {e:?}"),
         }
+        Ok(None) => log::info!("Nothing to send"),
+        Err(e) => log::error!("Error fetching unsent posts: {e:?}"),
+    }
+}
+
+/// Publishes a single post to its target right away, bypassing the schedule.
+pub async fn publish_now(
+    bot: Bot,
+    db: &Database,
+    cfg: &Config,
+    post: Post,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let Some(target) = db.fetch_target(post.target_id).await? else {
+        anyhow::bail!("target {} does not exist", post.target_id);
+    };
+
+    deliver(&bot, db, cfg, &target, vec![post], shutdown).await
+}
+
+/// Whether a message goes out silently and with its content protected from
+/// forwarding and saving.
+#[derive(Debug, Clone, Copy, Default)]
+struct SendOptions {
+    silent: bool,
+    protect_content: bool,
+}
 
-        tokio::time::sleep_until(Instant::now().add(cfg.interval)).await;
+impl SendOptions {
+    /// The post's own overrides win over the target's policy, which wins over
+    /// the configured one.
+    fn resolve(cfg: &Config, target: &Target, post: &Post, now: DateTime<Tz>) -> Self {
+        let target_silent_hours = target.silent_hours.as_deref().and_then(|value| {
+            value
+                .parse::<SilentHours>()
+                .inspect_err(|e| {
+                    log::error!("Invalid silent hours {value:?} of {}: {e:?}", target.name)
+                })
+                .ok()
+        });
+        let silent = post.silent.unwrap_or_else(|| {
+            target_silent_hours
+                .as_ref()
+                .or(cfg.silent_hours.as_ref())
+                .is_some_and(|hours| hours.contains(now))
+        });
+        let protect_content = post
+            .protect_content
+            .or(target.protect_content)
+            .unwrap_or(cfg.protect_content);
+        Self {
+            silent,
+            protect_content,
+        }
     }
 }
 
-async fn send_post(post: Post, bot: Bot, cfg: Config) -> anyhow::Result<()> {
-    let recipient = ChatId(cfg.target_chat_id);
-    let input_file = InputFile::file_id(post.file_id);
+/// Sends `posts` as a single message or album, waiting out flood limits, and
+/// records the outcome on every post. Albums carry the caption and publishing
+/// options of their first post. Fails without sending when another delivery
+/// has claimed any of the posts, and without recording anything when
+/// `shutdown` is requested while waiting out a flood limit.
+///
+/// Outside of [`SenderMode::Live`] the posts are only remembered as sent for
+/// the lifetime of the process, leaving the database untouched. Fired slots
+/// and the shuffle bag are kept in memory as well, see
+/// [`Database::simulate_sender_settings`].
+async fn deliver(
+    bot: &Bot,
+    db: &Database,
+    cfg: &Config,
+    target: &Target,
+    posts: Vec<Post>,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let post_ids: Vec<_> = posts.iter().map(|p| p.id).collect();
+    let Some(_claim) = db.claim_posts(&post_ids).await? else {
+        anyhow::bail!("post is already being sent or no longer queued");
+    };
+
+    let now = Utc::now().with_timezone(&cfg.timezone);
+    let caption = posts
+        .first()
+        .and_then(|post| render_caption(cfg, target, post, now));
+    let options = posts
+        .first()
+        .map(|post| SendOptions::resolve(cfg, target, post, now))
+        .unwrap_or_default();
 
-    match post.media_type {
-        MediaType::Photo => {
-            bot.send_photo(recipient, input_file).await?;
+    let recipient = match cfg.sender_mode {
+        SenderMode::Live => {
+            if !db.holds_lease(SENDER_LEASE, &cfg.instance_id).await? {
+                anyhow::bail!("this instance does not hold the sender lease");
+            }
+            ChatId(target.chat_id)
         }
-        MediaType::Video => {
-            bot.send_video(recipient, input_file).await?;
+        SenderMode::Preview => match cfg.preview_chat_id {
+            Some(chat_id) => ChatId(chat_id),
+            None => anyhow::bail!("preview mode requires a preview chat"),
+        },
+        SenderMode::DryRun => {
+            let items: Vec<String> = posts
+                .iter()
+                .map(|p| format!("{} {}", p.media_type, p.id))
+                .collect();
+            log::info!(
+                "[dry-run] would send to {}: {}, caption {caption:?}",
+                target.name,
+                items.join(", ")
+            );
+            db.mark_simulated_sent(posts.iter().map(|p| p.id)).await;
+            return Ok(());
         }
-        MediaType::Animation => {
-            bot.send_animation(recipient, input_file).await?;
+    };
+
+    let items = post_items(db, &posts).await?;
+    let result = loop {
+        let result = send_group_post(
+            items.clone(),
+            caption.clone(),
+            cfg.caption_parse_mode,
+            options,
+            bot.clone(),
+            recipient,
+        )
+        .await;
+        if let Err(e) = &result
+            && let Some(RequestError::RetryAfter(sec)) = e.downcast_ref::<RequestError>()
+        {
+            log::warn!("Rate limit: {} sec", &sec);
+            if shutdown
+                .run_until_cancelled(tokio::time::sleep(sec.duration()))
+                .await
+                .is_none()
+            {
+                anyhow::bail!("shutdown requested while waiting out the rate limit");
+            }
+            continue;
+        }
+        break result;
+    };
+
+    let ids = posts.iter().map(|p| p.id);
+    match result {
+        Ok(_) if cfg.sender_mode == SenderMode::Preview => {
+            log::info!(
+                "[preview] sent posts of {} to the preview chat",
+                target.name
+            );
+            db.mark_simulated_sent(ids).await;
+            Ok(())
+        }
+        Err(e) if cfg.sender_mode == SenderMode::Preview => {
+            db.record_simulated_failure(ids, cfg.max_attempts, cfg.retry_backoff)
+                .await;
+            Err(e)
+        }
+        Ok(messages) => {
+            db.mark_sent_posts(ids).await?;
+            log::info!("Marked as sent");
+
+            // Album messages come back in the order the media was sent.
+            for (item, message) in items.iter().zip(messages) {
+                if let Err(e) = db
+                    .add_message_id_for_post(item.post_id, message.chat.id.0, message.id.0)
+                    .await
+                {
+                    log::error!("Error saving published message id: {e:?}");
+                }
+            }
+            Ok(())
+        }
+        Err(e) => {
+            if let Err(db_error) = db
+                .record_send_failure(ids, e.to_string(), cfg.max_attempts, cfg.retry_backoff)
+                .await
+            {
+                log::error!("Unable to record send failure: {db_error:?}");
+            }
+            Err(e)
         }
     }
-    Ok(())
 }
 
-async fn send_group_photo_post(posts: Vec<Post>, bot: Bot, cfg: Config) -> anyhow::Result<()> {
-    if posts.len() == 0 {
-        return Ok(());
-    } else if posts.len() == 1 {
-        return send_post(posts[0].clone(), bot, cfg).await;
+async fn send_post(
+    item: PostItem,
+    caption: Option<String>,
+    parse_mode: ParseMode,
+    options: SendOptions,
+    bot: Bot,
+    recipient: ChatId,
+) -> anyhow::Result<Message> {
+    let (input_file, spoiler) = (InputFile::file_id(item.file_id), item.spoiler);
+
+    macro_rules! send {
+        ($request:expr) => {
+            $request
+                .disable_notification(options.silent)
+                .protect_content(options.protect_content)
+                .await?
+        };
     }
 
-    let recipient = ChatId(cfg.target_chat_id);
-    let group: Vec<InputMedia> = posts
+    let message = match (item.media_type, caption) {
+        (MediaType::Photo, None) => {
+            send!(bot.send_photo(recipient, input_file).has_spoiler(spoiler))
+        }
+        (MediaType::Photo, Some(caption)) => send!(
+            bot.send_photo(recipient, input_file)
+                .has_spoiler(spoiler)
+                .caption(caption)
+                .parse_mode(parse_mode)
+        ),
+        (MediaType::Video, None) => {
+            send!(bot.send_video(recipient, input_file).has_spoiler(spoiler))
+        }
+        (MediaType::Video, Some(caption)) => send!(
+            bot.send_video(recipient, input_file)
+                .has_spoiler(spoiler)
+                .caption(caption)
+                .parse_mode(parse_mode)
+        ),
+        (MediaType::Animation, None) => {
+            send!(
+                bot.send_animation(recipient, input_file)
+                    .has_spoiler(spoiler)
+            )
+        }
+        (MediaType::Animation, Some(caption)) => send!(
+            bot.send_animation(recipient, input_file)
+                .has_spoiler(spoiler)
+                .caption(caption)
+                .parse_mode(parse_mode)
+        ),
+        (MediaType::Document, None) => send!(bot.send_document(recipient, input_file)),
+        (MediaType::Document, Some(caption)) => send!(
+            bot.send_document(recipient, input_file)
+                .caption(caption)
+                .parse_mode(parse_mode)
+        ),
+        (MediaType::Text, Some(text)) => {
+            send!(bot.send_message(recipient, text).parse_mode(parse_mode))
+        }
+        (MediaType::Text, None) => anyhow::bail!("text post {} has no body", item.post_id),
+        (MediaType::Audio, None) => send!(bot.send_audio(recipient, input_file)),
+        (MediaType::Audio, Some(caption)) => send!(
+            bot.send_audio(recipient, input_file)
+                .caption(caption)
+                .parse_mode(parse_mode)
+        ),
+        // The Bot API client has no content protection for voice messages.
+        (MediaType::Voice, None) => {
+            bot.send_voice(recipient, input_file)
+                .disable_notification(options.silent)
+                .await?
+        }
+        (MediaType::Voice, Some(caption)) => {
+            bot.send_voice(recipient, input_file)
+                .caption(caption)
+                .parse_mode(parse_mode)
+                .disable_notification(options.silent)
+                .await?
+        }
+        // Stickers and video notes cannot carry a caption.
+        (MediaType::Sticker, _) => send!(bot.send_sticker(recipient, input_file)),
+        (MediaType::VideoNote, _) => send!(bot.send_video_note(recipient, input_file)),
+        (MediaType::Album, _) => anyhow::bail!("album {} has no items", item.post_id),
+    };
+    Ok(message)
+}
+
+async fn send_group_post(
+    items: Vec<PostItem>,
+    caption: Option<String>,
+    parse_mode: ParseMode,
+    options: SendOptions,
+    bot: Bot,
+    recipient: ChatId,
+) -> anyhow::Result<Vec<Message>> {
+    if items.is_empty() {
+        return Ok(vec![]);
+    } else if items.len() == 1 {
+        let message = send_post(
+            items[0].clone(),
+            caption,
+            parse_mode,
+            options,
+            bot,
+            recipient,
+        )
+        .await?;
+        return Ok(vec![message]);
+    }
+
+    let group: Vec<InputMedia> = items
         .iter()
-        .map(|p| InputMedia::Photo(InputMediaPhoto::new(InputFile::file_id(&p.file_id))))
+        .enumerate()
+        .map(|(i, item)| {
+            let caption = caption.clone().filter(|_| i == 0);
+            match item.media_type {
+                MediaType::Video => {
+                    let mut media = InputMediaVideo::new(InputFile::file_id(&item.file_id));
+                    media.has_spoiler = item.spoiler;
+                    InputMedia::Video(match caption {
+                        Some(caption) => media.caption(caption).parse_mode(parse_mode),
+                        None => media,
+                    })
+                }
+                _ => {
+                    let mut media = InputMediaPhoto::new(InputFile::file_id(&item.file_id));
+                    media.has_spoiler = item.spoiler;
+                    InputMedia::Photo(match caption {
+                        Some(caption) => media.caption(caption).parse_mode(parse_mode),
+                        None => media,
+                    })
+                }
+            }
+        })
         .collect();
 
-    bot.send_media_group(recipient, group).await?;
-    Ok(())
+    Ok(bot
+        .send_media_group(recipient, group)
+        .disable_notification(options.silent)
+        .protect_content(options.protect_content)
+        .await?)
+}
+
+/// The media making up `posts` in publishing order, with albums expanded to
+/// their items.
+async fn post_items(db: &Database, posts: &[Post]) -> anyhow::Result<Vec<PostItem>> {
+    let mut items = Vec::with_capacity(posts.len());
+    for post in posts {
+        match post.media_type {
+            MediaType::Album => items.extend(db.fetch_post_items(post.id).await?),
+            _ => items.push(PostItem::from(post)),
+        }
+    }
+    Ok(items)
 }
@@ -1,9 +1,19 @@
 use crate::{
     config::Config,
-    database::Database,
-    telegram_handlers::{handle_animation, handle_del, handle_photo, handle_unknown, handle_video},
+    database::{BOT_LEASE, Database},
+    telegram_handlers::{
+        Albums, album_item, handle_add_target, handle_album, handle_animation, handle_audio,
+        handle_callback, handle_caption, handle_channel_command, handle_del, handle_document,
+        handle_failed, handle_next, handle_order, handle_pause, handle_photo, handle_priority,
+        handle_protect, handle_published_forward, handle_recaption, handle_repost, handle_requeue,
+        handle_resume, handle_schedule, handle_send_now, handle_silent, handle_spoiler,
+        handle_stats, handle_sticker, handle_target, handle_targets, handle_text, handle_unknown,
+        handle_unpost, handle_video, handle_video_note, handle_voice, image_document,
+        published_post, text_post,
+    },
+    workers::lease::run_with_lease,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use teloxide::{
     Bot,
     dispatching::{Dispatcher, UpdateFilterExt},
@@ -13,43 +23,148 @@ use teloxide::{
     prelude::*,
     types::ReplyParameters,
 };
+use tokio_util::sync::CancellationToken;
 
 #[derive(BotCommands, Clone, Debug)]
 #[command(rename_rule = "lowercase")]
 pub enum Commands {
     #[command(aliases = ["del", "delete", "rem", "remove"])]
     Delete,
+    Target(String),
+    Targets,
+    AddTarget(String),
+    Order(String),
+    Next,
+    Priority(String),
+    SendNow,
+    Schedule(String),
+    Failed,
+    Requeue(String),
+    Caption(String),
+    Unpost,
+    Recaption(String),
+    Repost,
+    Stats,
+    Pause(String),
+    Resume,
+    Spoiler,
+    Silent(String),
+    Protect(String),
 }
 
-pub async fn run_bot(bot: Bot, db: Arc<Database>, cfg: Config) {
-    Dispatcher::builder(
+/// Polls for updates until `shutdown` is requested, but only while holding
+/// the bot lease, so that standby instances sharing the database do not
+/// conflict with the one polling.
+pub async fn run_bot(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let albums = Arc::new(Albums::default());
+    run_with_lease(BOT_LEASE, db.clone(), cfg.clone(), shutdown, |lease| {
+        dispatch(bot.clone(), db.clone(), cfg.clone(), albums.clone(), lease)
+    })
+    .await?;
+
+    log::info!("bot stopped");
+    Ok(())
+}
+
+/// Runs the dispatcher until `shutdown` is requested, letting handlers that
+/// are already running finish and storing the albums still pending.
+async fn dispatch(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    albums: Arc<Albums>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut dispatcher = Dispatcher::builder(
         bot,
-        Update::filter_message()
+        dptree::entry()
             .branch(
                 Update::filter_message()
-                    .filter(|msg: Message, cfg: Config| {
-                        !cfg.allowed_sender_chats.contains(&msg.chat.id.0)
-                    })
-                    .endpoint(|msg: Message, bot: Bot| async move {
-                        bot.send_message(msg.chat.id, "gtfo")
-                            .reply_parameters(ReplyParameters::new(msg.id))
-                            .await?;
-                        Ok(())
-                    }),
+                    .branch(
+                        Update::filter_message()
+                            .filter(|msg: Message, cfg: Config| {
+                                !cfg.allowed_sender_chats.contains(&msg.chat.id.0)
+                            })
+                            .endpoint(|msg: Message, bot: Bot| async move {
+                                bot.send_message(msg.chat.id, "gtfo")
+                                    .reply_parameters(ReplyParameters::new(msg.id))
+                                    .await?;
+                                Ok(())
+                            }),
+                    )
+                    .branch(
+                        Update::filter_message()
+                            .filter_command::<Commands>()
+                            .branch(case![Commands::Delete].endpoint(handle_del))
+                            .branch(case![Commands::Target(name)].endpoint(handle_target))
+                            .branch(case![Commands::Targets].endpoint(handle_targets))
+                            .branch(case![Commands::AddTarget(args)].endpoint(handle_add_target))
+                            .branch(case![Commands::Order(order)].endpoint(handle_order))
+                            .branch(case![Commands::Next].endpoint(handle_next))
+                            .branch(case![Commands::Priority(level)].endpoint(handle_priority))
+                            .branch(case![Commands::SendNow].endpoint(handle_send_now))
+                            .branch(case![Commands::Schedule(datetime)].endpoint(handle_schedule))
+                            .branch(case![Commands::Failed].endpoint(handle_failed))
+                            .branch(case![Commands::Requeue(args)].endpoint(handle_requeue))
+                            .branch(case![Commands::Caption(args)].endpoint(handle_caption))
+                            .branch(case![Commands::Unpost].endpoint(handle_unpost))
+                            .branch(case![Commands::Recaption(text)].endpoint(handle_recaption))
+                            .branch(case![Commands::Repost].endpoint(handle_repost))
+                            .branch(case![Commands::Stats].endpoint(handle_stats))
+                            .branch(case![Commands::Pause(duration)].endpoint(handle_pause))
+                            .branch(case![Commands::Resume].endpoint(handle_resume))
+                            .branch(case![Commands::Spoiler].endpoint(handle_spoiler))
+                            .branch(case![Commands::Silent(args)].endpoint(handle_silent))
+                            .branch(case![Commands::Protect(args)].endpoint(handle_protect)),
+                    )
+                    .branch(
+                        dptree::filter_map_async(published_post).endpoint(handle_published_forward),
+                    )
+                    .branch(dptree::filter(album_item).endpoint(handle_album))
+                    .branch(Message::filter_photo().endpoint(handle_photo))
+                    .branch(Message::filter_video().endpoint(handle_video))
+                    .branch(Message::filter_animation().endpoint(handle_animation))
+                    .branch(dptree::filter(image_document).endpoint(handle_document))
+                    .branch(Message::filter_audio().endpoint(handle_audio))
+                    .branch(Message::filter_voice().endpoint(handle_voice))
+                    .branch(Message::filter_sticker().endpoint(handle_sticker))
+                    .branch(Message::filter_video_note().endpoint(handle_video_note))
+                    .branch(dptree::filter(text_post).endpoint(handle_text))
+                    .branch(dptree::endpoint(handle_unknown)),
             )
             .branch(
-                Update::filter_message()
+                Update::filter_channel_post()
                     .filter_command::<Commands>()
-                    .branch(case![Commands::Delete].endpoint(handle_del)),
+                    .endpoint(handle_channel_command),
             )
-            .branch(Message::filter_photo().endpoint(handle_photo))
-            .branch(Message::filter_video().endpoint(handle_video))
-            .branch(Message::filter_animation().endpoint(handle_animation))
-            .branch(dptree::endpoint(handle_unknown)),
+            .branch(Update::filter_callback_query().endpoint(handle_callback)),
     )
-    .dependencies(dptree::deps![db.clone(), cfg.clone()])
-    .enable_ctrlc_handler()
-    .build()
-    .dispatch()
-    .await;
+    .dependencies(dptree::deps![
+        db.clone(),
+        cfg.clone(),
+        albums.clone(),
+        shutdown.clone()
+    ])
+    .build();
+
+    let shutdown_token = dispatcher.shutdown_token();
+    tokio::spawn(async move {
+        shutdown.cancelled().await;
+        // The dispatcher refuses to shut down until it has started polling.
+        loop {
+            match shutdown_token.shutdown() {
+                Ok(done) => break done.await,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+    });
+
+    dispatcher.dispatch().await;
+    albums.flush().await;
+    Ok(())
 }
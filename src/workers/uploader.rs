@@ -1,16 +1,19 @@
 use crate::{
     config::Config,
-    database::{Database, MediaType, UploadTask},
+    database::{Database, MediaType, NewPost, UploadTask},
+    telegram_handlers::post_keyboard,
 };
 use std::sync::Arc;
-use teloxide::{
-    RequestError,
-    prelude::*,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile},
-};
+use teloxide::{RequestError, prelude::*, types::InputFile};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-pub async fn run_uploader(bot: Bot, db: Arc<Database>, cfg: Config) -> anyhow::Result<()> {
+pub async fn run_uploader(
+    bot: Bot,
+    db: Arc<Database>,
+    cfg: Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
     while let Ok(Some(upload_task)) = db.fetch_unprocessed_upload_task().await {
         log::info!("Uploading queued photo...");
         upload(
@@ -22,8 +25,11 @@ pub async fn run_uploader(bot: Bot, db: Arc<Database>, cfg: Config) -> anyhow::R
         .await;
     }
 
-    loop {
-        db.upload_task_added.notified().await;
+    while shutdown
+        .run_until_cancelled(db.upload_task_added.notified())
+        .await
+        .is_some()
+    {
         while let Ok(Some(upload_task)) = db.fetch_unprocessed_upload_task().await {
             log::info!("Uploading photo...");
             upload(
@@ -35,6 +41,9 @@ pub async fn run_uploader(bot: Bot, db: Arc<Database>, cfg: Config) -> anyhow::R
             .await;
         }
     }
+
+    log::info!("uploader stopped");
+    Ok(())
 }
 
 async fn upload(bot: Bot, chat_id: i64, db: Arc<Database>, upload_task: UploadTask) {
@@ -42,17 +51,12 @@ async fn upload(bot: Bot, chat_id: i64, db: Arc<Database>, upload_task: UploadTa
         MediaType::Photo => {
             let post_id = Uuid::now_v7();
 
-            let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
-                "Delete",
-                format!("del {}", post_id),
-            )]]);
-
             let msg = match bot
                 .send_photo(
                     ChatId(chat_id),
                     InputFile::memory(upload_task.data.as_slice().to_owned()),
                 )
-                .reply_markup(keyboard)
+                .reply_markup(post_keyboard(post_id, false))
                 .await
             {
                 Ok(v) => v,
@@ -89,10 +93,11 @@ async fn upload(bot: Bot, chat_id: i64, db: Arc<Database>, upload_task: UploadTa
 
             match db
                 .create_post(
-                    Some(post_id),
-                    MediaType::Photo,
-                    file_meta.id.clone(),
-                    Some(upload_task.image_hash.clone().unwrap()),
+                    NewPost {
+                        id: Some(post_id),
+                        image_hash: Some(upload_task.image_hash.clone().unwrap()),
+                        ..NewPost::new(MediaType::Photo, file_meta.id.clone())
+                    },
                     msg.chat.id.0,
                     msg.id.0,
                 )